@@ -0,0 +1,278 @@
+//! A ring buffer with atomic counters so many threads can record into it
+//! without a Mutex
+use std::sync::{
+	atomic::{AtomicUsize, Ordering},
+	Arc,
+};
+
+use crate::{ring_buffer::RingBuffer, window::Window};
+
+/// A node of the [AtomicRingBuffer]
+#[derive(Debug, Default)]
+struct AtomicNode {
+	failure_count: AtomicUsize,
+	success_count: AtomicUsize,
+}
+
+impl AtomicNode {
+	fn reset(&self) {
+		self.failure_count.store(0, Ordering::Relaxed);
+		self.success_count.store(0, Ordering::Relaxed);
+	}
+}
+
+/// A [RingBuffer] whose node counts are atomic, making
+/// [AtomicRingBuffer::record_success] and [AtomicRingBuffer::record_failure]
+/// lock-free via `&self`.
+///
+/// Only the counting is lock-free. Advancing the buffer and evaluating the
+/// error rate should be coordinated by a single owner, e.g. a timer thread, as
+/// events racing with an advance may land in either the old or the new span.
+/// A [CircuitBreaker](crate::CircuitBreaker) does this itself for the buffer
+/// returned by its `atomic_buffer`.
+#[derive(Debug)]
+pub struct AtomicRingBuffer {
+	cursor: AtomicUsize,
+	nodes: Vec<AtomicNode>,
+}
+
+impl AtomicRingBuffer {
+	/// Create a new atomic ring buffer with `elements` nodes
+	pub fn new(elements: usize) -> Self {
+		if elements == 0 {
+			panic!("You must at least have one buffer node in your ring buffer");
+		}
+
+		Self {
+			cursor: AtomicUsize::new(0),
+			nodes: (0..elements).map(|_| AtomicNode::default()).collect(),
+		}
+	}
+
+	/// Returns the size of the buffer
+	pub fn get_size(&self) -> usize {
+		self.nodes.len()
+	}
+
+	/// Returns the current cursor
+	pub fn get_cursor(&self) -> usize {
+		self.cursor.load(Ordering::Acquire)
+	}
+
+	/// Record a success into the current node
+	pub fn record_success(&self) {
+		self.nodes[self.get_cursor()].success_count.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Record a failure into the current node
+	pub fn record_failure(&self) {
+		self.nodes[self.get_cursor()].failure_count.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Move the cursor forward by `steps` positions (modulo buffer size),
+	/// resetting the nodes we skip and the node we land on, the same as
+	/// [RingBuffer::advance]
+	// Allowing modulo with size in this method because size can't be less than 1
+	// or we panic on creation of the buffer in the new method
+	#[allow(clippy::arithmetic_side_effects)]
+	pub fn advance(&self, steps: usize) {
+		let size = self.get_size();
+		let cursor = self.get_cursor();
+		if steps >= size {
+			for node in &self.nodes {
+				node.reset();
+			}
+		} else {
+			for step in 1..=steps {
+				self.nodes[(cursor + step) % size].reset();
+			}
+		}
+		let new_cursor = (cursor + steps % size) % size;
+		self.cursor.store(new_cursor, Ordering::Release);
+		self.nodes[new_cursor].reset();
+	}
+
+	/// Take the counts of all nodes, resetting them to zero, and return them as
+	/// a [RingBuffer] with the same cursor, e.g. to merge them into another
+	/// buffer span by span. Events recorded concurrently are never lost, they
+	/// are either part of the returned buffer or of the next take.
+	pub fn take(&self) -> RingBuffer {
+		let mut buffer = RingBuffer::new(self.get_size());
+		for (i, node) in self.nodes.iter().enumerate() {
+			buffer.rotate_to(i);
+			buffer.add_failures(node.failure_count.swap(0, Ordering::Relaxed));
+			buffer.add_successes(node.success_count.swap(0, Ordering::Relaxed));
+		}
+		buffer.rotate_to(self.get_cursor());
+		buffer
+	}
+
+	/// Get a [RingBuffer] copy of the current counts, e.g. to evaluate the error
+	/// rate or render the buffer
+	pub fn snapshot(&self) -> RingBuffer {
+		let mut buffer = RingBuffer::new(self.get_size());
+		for (i, node) in self.nodes.iter().enumerate() {
+			buffer.rotate_to(i);
+			buffer.add_failures(node.failure_count.load(Ordering::Relaxed));
+			buffer.add_successes(node.success_count.load(Ordering::Relaxed));
+		}
+		buffer.rotate_to(self.get_cursor());
+		buffer
+	}
+}
+
+impl Window for AtomicRingBuffer {
+	fn add_success(&mut self) {
+		self.record_success();
+	}
+
+	fn add_failure(&mut self) {
+		self.record_failure();
+	}
+
+	fn evaluated_events(&self) -> usize {
+		self.snapshot().evaluated_events()
+	}
+
+	fn get_error_rate(&self, min_eval_size: usize) -> f32 {
+		self.snapshot().get_error_rate(min_eval_size)
+	}
+}
+
+/// The [AtomicRingBuffer] a [CircuitBreaker](crate::CircuitBreaker) hands out
+/// via `atomic_buffer`, created on first use.
+///
+/// Its counts are taken by a single breaker, so cloning results in no buffer
+/// at all instead of sharing it and it always compares equal, the same as
+/// [Hooks](crate::hooks::Hooks).
+#[derive(Debug, Default)]
+pub(crate) struct SharedAtomicBuffer(Option<Arc<AtomicRingBuffer>>);
+
+impl SharedAtomicBuffer {
+	/// Returns the buffer, creating it with `elements` nodes if there is none
+	pub(crate) fn get_or_create(&mut self, elements: usize) -> Arc<AtomicRingBuffer> {
+		Arc::clone(self.0.get_or_insert_with(|| Arc::new(AtomicRingBuffer::new(elements))))
+	}
+
+	/// Returns the buffer if it has been created
+	pub(crate) fn get(&self) -> Option<Arc<AtomicRingBuffer>> {
+		self.0.clone()
+	}
+}
+
+impl Clone for SharedAtomicBuffer {
+	fn clone(&self) -> Self {
+		Self::default()
+	}
+}
+
+impl PartialEq for SharedAtomicBuffer {
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{sync::Arc, thread};
+
+	use super::*;
+	use crate::ring_buffer::NodeInfo;
+
+	#[test]
+	#[should_panic]
+	fn new_invalid_test() {
+		AtomicRingBuffer::new(0);
+	}
+
+	#[test]
+	fn advance_test() {
+		let buffer = AtomicRingBuffer::new(3);
+		buffer.record_failure();
+		buffer.advance(1);
+		buffer.record_success();
+		assert_eq!(buffer.get_cursor(), 1);
+		assert_eq!(buffer.get_error_rate(1), 100.0);
+
+		buffer.advance(2);
+		assert_eq!(buffer.get_cursor(), 0);
+		assert_eq!(
+			buffer.snapshot().get_node_info(0),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(buffer.snapshot().get_node_info(1).success_count, 1);
+
+		buffer.advance(7);
+		assert_eq!(buffer.get_cursor(), 1);
+		assert_eq!(buffer.snapshot().get_node_info(1).success_count, 0);
+	}
+
+	#[test]
+	fn advance_matches_ring_buffer_test() {
+		let atomic = AtomicRingBuffer::new(3);
+		let mut buffer = RingBuffer::new(3);
+		for steps in [0, 1, 0, 2, 3, 5, 0] {
+			atomic.record_failure();
+			atomic.record_success();
+			atomic.record_success();
+			buffer.add_failure();
+			buffer.add_successes(2);
+
+			atomic.advance(steps);
+			buffer.advance(steps);
+			assert_eq!(atomic.snapshot(), buffer, "after advancing {steps} steps");
+		}
+	}
+
+	#[test]
+	fn take_test() {
+		let buffer = AtomicRingBuffer::new(3);
+		buffer.record_failure();
+		buffer.advance(1);
+		buffer.record_success();
+		buffer.record_success();
+
+		let taken = buffer.take();
+		assert_eq!(taken.get_cursor(), 1);
+		assert_eq!(taken.get_node_info(0).failure_count, 1);
+		assert_eq!(taken.get_node_info(1).success_count, 2);
+		assert!(buffer.take().has_no_events());
+		assert_eq!(buffer.get_cursor(), 1);
+	}
+
+	#[test]
+	fn concurrent_record_test() {
+		let threads = 8;
+		let per_thread = 10_000;
+		let buffer = Arc::new(AtomicRingBuffer::new(4));
+
+		let handles: Vec<_> = (0..threads)
+			.map(|i| {
+				let buffer = Arc::clone(&buffer);
+				thread::spawn(move || {
+					for _ in 0..per_thread {
+						if i % 2 == 0 {
+							buffer.record_success();
+						} else {
+							buffer.record_failure();
+						}
+					}
+				})
+			})
+			.collect();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+
+		assert_eq!(
+			buffer.snapshot().get_node_info(0),
+			NodeInfo {
+				failure_count: threads / 2 * per_thread,
+				success_count: threads / 2 * per_thread,
+			}
+		);
+	}
+}
@@ -1,9 +1,22 @@
 //! This is the main circuit breaker implementation
 //! It allows you to give your system a break when a threshhold of errors has
 //! been reached.
-use std::time::{Duration, Instant};
+use std::{
+	collections::HashMap,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
-use crate::ring_buffer::RingBuffer;
+use crate::{
+	atomic_ring_buffer::{AtomicRingBuffer, SharedAtomicBuffer},
+	clock::{BoxedClock, Clock, SystemClock},
+	error::{CircuitError, Rejected, SettingsError},
+	guard::{CallGuard, Permit},
+	hooks::Hooks,
+	ring_buffer::{percentage, NodeInfo, RingBuffer, LATENCY_BUCKET_BOUNDS_MS},
+	rolling_log::RollingLog,
+	window::{Window, WindowMode},
+};
 
 /// The state of our [CircuitBreaker]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -26,27 +39,268 @@ impl std::fmt::Display for State {
 				if alt {
 					write!(f, "│")
 				} else {
-					write!(f, "Closed     ")
+					write!(f, "{:<11}", self.label())
 				}
 			},
 			State::Open(_) => {
 				if alt {
 					write!(f, "\x1b[0m─")
 				} else {
-					write!(f, "\x1b[41m Open \x1b[0m     ")
+					write!(f, "{} {} \x1b[0m     ", self.ansi_color(), self.label())
 				}
 			},
 			State::HalfOpen => {
 				if alt {
 					write!(f, "/")
 				} else {
-					write!(f, "\x1b[43m Half Open \x1b[0m")
+					write!(f, "{} {} \x1b[0m", self.ansi_color(), self.label())
 				}
 			},
 		}
 	}
 }
 
+impl State {
+	/// Returns the human readable name of the state
+	pub fn label(&self) -> &'static str {
+		match self {
+			State::Closed => "Closed",
+			State::Open(_) => "Open",
+			State::HalfOpen => "Half Open",
+		}
+	}
+
+	/// Returns the ANSI escape code for the background color of the state or an
+	/// empty string if the state isn't highlighted
+	pub fn ansi_color(&self) -> &'static str {
+		match self {
+			State::Closed => "",
+			State::Open(_) => "\x1b[41m",
+			State::HalfOpen => "\x1b[43m",
+		}
+	}
+
+	/// Returns how severe this state is, `Closed` being the least and `Open` the
+	/// most severe, so states can be compared with each other
+	pub fn severity(&self) -> u8 {
+		match self {
+			State::Closed => 0,
+			State::HalfOpen => 1,
+			State::Open(_) => 2,
+		}
+	}
+
+	/// Returns the state for a code from [CircuitBreaker::quantized_state] or
+	/// `None` for an unknown code. The code doesn't carry when the circuit
+	/// opened, so `Open` is set to now.
+	pub fn from_u8(code: u8) -> Option<State> {
+		match code {
+			0 => Some(State::Closed),
+			1 => Some(State::HalfOpen),
+			2 => Some(State::Open(Instant::now())),
+			_ => None,
+		}
+	}
+}
+
+/// A single state transition of the [CircuitBreaker]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransitionRecord {
+	/// When the transition happened
+	pub at: Instant,
+	/// The state we left
+	pub from: State,
+	/// The state we entered
+	pub to: State,
+}
+
+/// Counters accumulated by the [CircuitBreaker] since they were last drained
+/// via [CircuitBreaker::drain_metrics]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MetricsSnapshot {
+	/// The number of events recorded while the circuit wasn't open
+	pub events_recorded: usize,
+	/// The number of calls `call`, `guard` or `acquire` didn't permit because
+	/// the circuit was open
+	pub rejected_count: usize,
+	/// The number of state transitions
+	pub transition_count: usize,
+}
+
+/// The total time the [CircuitBreaker] spent in each [State]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StateDurations {
+	pub closed: Duration,
+	pub open: Duration,
+	pub half_open: Duration,
+}
+
+impl StateDurations {
+	fn add(&mut self, state: State, duration: Duration) {
+		let total = match state {
+			State::Closed => &mut self.closed,
+			State::Open(_) => &mut self.open,
+			State::HalfOpen => &mut self.half_open,
+		};
+		*total = total.saturating_add(duration);
+	}
+}
+
+/// How often the [CircuitBreaker] transitioned into each [State]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StateEntryCounts {
+	pub closed: usize,
+	pub open: usize,
+	pub half_open: usize,
+}
+
+/// The state of the [CircuitBreaker] together with the inputs that determined
+/// it, see [CircuitBreaker::explain_state]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateExplanation {
+	/// The state after evaluating it
+	pub state: State,
+	/// The error rate compared against the threshold
+	pub error_rate: f32,
+	/// The number of events the error rate is calculated from
+	pub evaluated_events: usize,
+	/// The number of events required to evaluate the error rate at all
+	pub min_eval_size: usize,
+	/// The successes recorded during the current half-open trial
+	pub trial_success: usize,
+	/// The successes required to close a half-open circuit
+	pub trial_success_required: usize,
+	/// The time left until the state transitions on its own or `None` if the
+	/// next transition depends on recorded events
+	pub time_until_transition: Option<Duration>,
+	/// Why the circuit opened most recently
+	pub open_reason: Option<OpenReason>,
+}
+
+/// The timing math behind advancing the ring buffer, see
+/// [CircuitBreaker::debug_timing]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingInfo {
+	/// The time since the ring buffer last advanced or was recorded into
+	pub last_record_age: Duration,
+	/// The time spent in the current span, the remainder of `last_record_age`
+	/// after whole spans
+	pub span_elapsed: Duration,
+	/// The number of spans the ring buffer moves forward on the next advance
+	pub spans_since_last_record: u128,
+}
+
+/// What happened during [CircuitBreaker::probe]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeResult<T, E> {
+	/// True if the probe call was made
+	pub ran: bool,
+	/// True if the probe closed the half-open circuit
+	pub closed: bool,
+	/// The result of the probe call if it was made
+	pub result: Option<Result<T, E>>,
+}
+
+/// A span of the ring buffer together with the time range it covers, see
+/// [CircuitBreaker::spans]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpanView {
+	/// The index of the node in the ring buffer
+	pub index: usize,
+	/// When the span started, inclusive
+	pub start: Instant,
+	/// When the span ends, exclusive
+	pub end: Instant,
+	/// The events recorded into the span
+	pub info: NodeInfo,
+	/// True for the span we currently record into
+	pub active: bool,
+}
+
+/// How the error rate threshold that trips the [CircuitBreaker] is determined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdMode {
+	/// Trip when the error rate exceeds `Settings.error_threshold`
+	Absolute,
+	/// Trip when the error rate exceeds the natural `baseline` error rate of the
+	/// service by more than `margin` percentage points
+	RelativeToBaseline { baseline: f32, margin: f32 },
+}
+
+/// Which spans of the ring buffer the error rate is calculated from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DenominatorPolicy {
+	/// All completed spans
+	WholeWindow,
+	/// Only the most recently completed span, which reacts faster but is noisier
+	TrailingSpan,
+	/// The N most recently completed spans, at least one
+	LastN(usize),
+}
+
+impl DenominatorPolicy {
+	/// The number of completed spans before the current one the error rate is
+	/// calculated from
+	fn spans(&self) -> usize {
+		match self {
+			DenominatorPolicy::WholeWindow => usize::MAX,
+			DenominatorPolicy::TrailingSpan => 1,
+			DenominatorPolicy::LastN(spans) => *spans,
+		}
+	}
+}
+
+/// A condition that trips a closed [CircuitBreaker]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TripKind {
+	/// The error rate reached the threshold
+	ErrorRate,
+	/// `Settings.trip_on_consecutive` failures happened in a row
+	ConsecutiveFailures,
+	/// The approximate p95 latency exceeded `Settings.p95_slow_threshold`
+	SlowCalls,
+}
+
+impl TripKind {
+	/// All trip conditions in their default priority
+	pub const ALL: [TripKind; 3] = [TripKind::ErrorRate, TripKind::ConsecutiveFailures, TripKind::SlowCalls];
+
+	/// The default `Settings.trip_priority`
+	pub const DEFAULT_PRIORITY: [TripKind; 3] = TripKind::ALL;
+}
+
+/// Why the [CircuitBreaker] opened most recently
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpenReason {
+	/// A closed circuit tripped on this condition
+	Trip(TripKind),
+	/// A half-open circuit failed its trial
+	TrialFailed,
+}
+
+/// The classified result of a request, see [CircuitBreaker::record_outcome]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+	/// The request succeeded
+	Success,
+	/// The request failed
+	Failure,
+	/// The request timed out, which counts as a failure
+	Timeout,
+	/// The request shouldn't influence the circuit, e.g. a client error
+	Ignore,
+}
+
+/// What an open [CircuitBreaker] does with calls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpenPolicy {
+	/// Reject all calls while open
+	FailClosed,
+	/// Permit all calls while open, e.g. for non-critical features, but don't
+	/// record them
+	FailOpen,
+}
+
 /// The possible settings for our [CircuitBreaker]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Settings {
@@ -58,14 +312,92 @@ pub struct Settings {
 	/// Define the minimum number of events required in the buffer to evaluate the
 	/// error rate
 	pub min_eval_size: usize,
-	/// Set the error rate percentage that will trigger the circuit to open
+	/// Set the error rate percentage that will trigger the circuit to open. An
+	/// error rate exactly at the threshold only trips with
+	/// `inclusive_threshold`
 	pub error_threshold: f32,
+	/// Choose between the absolute `error_threshold` or a threshold relative to
+	/// a baseline error rate
+	pub threshold_mode: ThresholdMode,
 	/// Specify the duration (in seconds) the [CircuitBreaker] remains open before
-	/// transitioning to half-open
+	/// transitioning to half-open. Zero means probe immediately: an open circuit
+	/// moves to half-open on the next evaluation but still needs
+	/// `trial_success_required` successes to close.
 	pub retry_timeout: Duration,
 	/// Set the number of consecutive successes required to close a half-open
 	/// circuit
 	pub trial_success_required: usize,
+	/// Choose between recording into time-based spans or a rolling log of the
+	/// last N events
+	pub window_mode: WindowMode,
+	/// Set the number of most recent state transitions kept in memory
+	pub transition_log_size: usize,
+	/// Run in observe-only mode: the state machine advances as usual but calls
+	/// are always permitted
+	pub shadow: bool,
+	/// Specify the duration (in seconds) after closing a half-open circuit
+	/// during which it won't open again, even above the threshold
+	pub close_cooldown: Duration,
+	/// Close a half-open circuit by error rate instead of consecutive
+	/// successes: admit a sample of `trial_success_required` events and close
+	/// if their error rate is below this percentage, otherwise open again
+	pub recover_on_error_rate: Option<f32>,
+	/// Include the current, still filling span of the ring buffer when
+	/// calculating the error rate. This detects errors faster but a span that
+	/// just started holds few events and makes the error rate noisy.
+	pub include_current_span: bool,
+	/// Open a closed circuit right away after this many consecutive failures,
+	/// even before `min_eval_size` events were recorded
+	pub trip_on_consecutive: Option<usize>,
+	/// Trip when the error rate is at or above the threshold (`>=`) instead of
+	/// strictly above it (`>`)
+	pub inclusive_threshold: bool,
+	/// The order trip conditions are checked in when several are met at once,
+	/// which decides the reported [OpenReason]. Conditions missing from it are
+	/// checked last.
+	pub trip_priority: [TripKind; 3],
+	/// Keep evaluating the error rate from before a long idle gap cleared the
+	/// whole ring buffer for one more evaluation, instead of reading a healthy
+	/// 0% right away
+	pub sticky_after_idle: bool,
+	/// Choose if an open circuit rejects calls or permits them without
+	/// recording them
+	pub open_policy: OpenPolicy,
+	/// Record half-open probes into a ring buffer of this many probes and
+	/// evaluate its error rate against the threshold like a closed circuit once
+	/// `trial_success_required` probes (or as many as fit) were made: close if it
+	/// doesn't trip, otherwise open again. Takes precedence over
+	/// `recover_on_error_rate`.
+	pub half_open_buffer_size: Option<usize>,
+	/// Choose which completed spans of the ring buffer the error rate is
+	/// calculated from. Ignored in `WindowMode::RollingLog`.
+	pub denominator_policy: DenominatorPolicy,
+	/// Open a closed circuit when the approximate p95 latency of the calls
+	/// recorded via `record_timed` exceeds this duration, see
+	/// [CircuitBreaker::approx_p95]
+	pub p95_slow_threshold: Option<Duration>,
+	/// Evaluate the state in `get_state` at most once per this interval and keep
+	/// the last result in between, e.g. for very high call rates. Trips and
+	/// transitions are delayed by up to this interval unless recording
+	/// evaluates the state first, which is never debounced.
+	pub eval_debounce: Option<Duration>,
+	/// Open a circuit that closed after a half-open trial on the first failure
+	/// within this duration after closing, even during `close_cooldown`, to
+	/// catch a false recovery fast. Reports [OpenReason::TrialFailed].
+	pub post_recovery_sensitive: Duration,
+	/// Take back one half-open trial success for each elapsed interval without
+	/// a new success, so only sustained, recent successes close the circuit,
+	/// e.g. half the `retry_timeout`. `None` or zero never decays.
+	pub trial_decay_interval: Option<Duration>,
+	/// The largest `buffer_size`, `half_open_buffer_size` and
+	/// `WindowMode::RollingLog` capacity accepted by [Settings::validate], so a
+	/// typo doesn't attempt a multi-gigabyte allocation. Defaults to 1_000_000,
+	/// raise it for larger buffers.
+	pub max_buffer_size: usize,
+	/// Calculate the error rate from the current span while every other span
+	/// of the ring buffer is empty, e.g. right after startup, instead of
+	/// reading 0% during a total outage. Ignored in `WindowMode::RollingLog`.
+	pub evaluate_cursor_when_window_empty: bool,
 }
 
 impl Default for Settings {
@@ -75,14 +407,217 @@ impl Default for Settings {
 			buffer_span_duration: Duration::from_secs(200),
 			min_eval_size: 100,
 			error_threshold: 10.0,
+			threshold_mode: ThresholdMode::Absolute,
 			retry_timeout: Duration::from_millis(60000),
 			trial_success_required: 20,
+			window_mode: WindowMode::Spans,
+			transition_log_size: 10,
+			shadow: false,
+			close_cooldown: Duration::ZERO,
+			recover_on_error_rate: None,
+			include_current_span: false,
+			trip_on_consecutive: None,
+			inclusive_threshold: false,
+			trip_priority: TripKind::DEFAULT_PRIORITY,
+			sticky_after_idle: false,
+			open_policy: OpenPolicy::FailClosed,
+			half_open_buffer_size: None,
+			denominator_policy: DenominatorPolicy::WholeWindow,
+			p95_slow_threshold: None,
+			eval_debounce: None,
+			post_recovery_sensitive: Duration::ZERO,
+			trial_decay_interval: None,
+			max_buffer_size: 1_000_000,
+			evaluate_cursor_when_window_empty: false,
+		}
+	}
+}
+
+impl Settings {
+	/// Check that a [CircuitBreaker] can be created with these settings
+	pub fn validate(&self) -> Result<(), SettingsError> {
+		if self.buffer_size == 0 {
+			return Err(SettingsError::ZeroBufferSize);
+		}
+		self.check_max_buffer_size()?;
+		if self.window_mode == WindowMode::RollingLog(0) {
+			return Err(SettingsError::ZeroRollingLogCapacity);
+		}
+		if self.half_open_buffer_size == Some(0) {
+			return Err(SettingsError::ZeroHalfOpenBufferSize);
+		}
+		if self.denominator_policy == DenominatorPolicy::LastN(0) {
+			return Err(SettingsError::ZeroLastNSpans);
+		}
+		if self.error_threshold.is_nan() || self.error_threshold < 0.0 {
+			return Err(SettingsError::InvalidErrorThreshold(self.error_threshold));
+		}
+
+		Ok(())
+	}
+
+	/// Check that no buffer is larger than `max_buffer_size`
+	pub fn check_max_buffer_size(&self) -> Result<(), SettingsError> {
+		let max = self.max_buffer_size;
+		if self.buffer_size > max {
+			return Err(SettingsError::BufferSizeTooLarge {
+				size: self.buffer_size,
+				max,
+			});
+		}
+		if let Some(size) = self.half_open_buffer_size.filter(|size| *size > max) {
+			return Err(SettingsError::HalfOpenBufferSizeTooLarge { size, max });
+		}
+		if let WindowMode::RollingLog(capacity) = self.window_mode {
+			if capacity > max {
+				return Err(SettingsError::RollingLogCapacityTooLarge { capacity, max });
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Read the [Settings] from environment variables, falling back to the
+	/// defaults for unset variables, and validate them:
+	///
+	/// - `CB_BUFFER_SIZE`: `buffer_size`
+	/// - `CB_BUFFER_SPAN_DURATION`: `buffer_span_duration`, see [parse_duration]
+	/// - `CB_MIN_EVAL_SIZE`: `min_eval_size`
+	/// - `CB_ERROR_THRESHOLD`: `error_threshold`
+	/// - `CB_RETRY_TIMEOUT`: `retry_timeout`, see [parse_duration]
+	/// - `CB_TRIAL_SUCCESS_REQUIRED`: `trial_success_required`
+	pub fn from_env() -> Result<Settings, SettingsError> {
+		Self::from_lookup(|name| std::env::var(name).ok())
+	}
+
+	fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Settings, SettingsError> {
+		fn read<T>(
+			lookup: &impl Fn(&str) -> Option<String>,
+			name: &str,
+			parse: impl Fn(&str) -> Option<T>,
+			default: T,
+		) -> Result<T, SettingsError> {
+			match lookup(name) {
+				None => Ok(default),
+				Some(value) => parse(&value).ok_or(SettingsError::InvalidEnvVar {
+					name: String::from(name),
+					value,
+				}),
+			}
+		}
+
+		let default = Settings::default();
+		let settings = Settings {
+			buffer_size: read(&lookup, "CB_BUFFER_SIZE", |v| v.parse().ok(), default.buffer_size)?,
+			buffer_span_duration: read(&lookup, "CB_BUFFER_SPAN_DURATION", parse_duration, default.buffer_span_duration)?,
+			min_eval_size: read(&lookup, "CB_MIN_EVAL_SIZE", |v| v.parse().ok(), default.min_eval_size)?,
+			error_threshold: read(&lookup, "CB_ERROR_THRESHOLD", |v| v.parse().ok(), default.error_threshold)?,
+			retry_timeout: read(&lookup, "CB_RETRY_TIMEOUT", parse_duration, default.retry_timeout)?,
+			trial_success_required: read(
+				&lookup,
+				"CB_TRIAL_SUCCESS_REQUIRED",
+				|v| v.parse().ok(),
+				default.trial_success_required,
+			)?,
+			..default
+		};
+
+		settings.validate()?;
+		Ok(settings)
+	}
+
+	/// The names of all presets available via `Settings::preset`
+	pub const PRESETS: [&'static str; 3] = ["default", "aggressive", "conservative"];
+
+	/// Get the [Settings] of a named preset:
+	/// - `default`: the same as `Settings::default()`
+	/// - `aggressive`: trips early on few errors and probes for recovery quickly
+	/// - `conservative`: needs a lot of data and errors to trip and recovers
+	///   slowly
+	pub fn preset(name: &str) -> Option<Settings> {
+		match name {
+			"default" => Some(Settings::default()),
+			"aggressive" => Some(Settings {
+				buffer_span_duration: Duration::from_secs(10),
+				min_eval_size: 20,
+				error_threshold: 5.0,
+				retry_timeout: Duration::from_secs(30),
+				trial_success_required: 10,
+				..Settings::default()
+			}),
+			"conservative" => Some(Settings {
+				buffer_size: 10,
+				buffer_span_duration: Duration::from_secs(60),
+				min_eval_size: 200,
+				error_threshold: 25.0,
+				retry_timeout: Duration::from_secs(120),
+				trial_success_required: 50,
+				..Settings::default()
+			}),
+			_ => None,
+		}
+	}
+
+	/// Check if switching from these settings to `other` changes the layout of
+	/// the ring buffer or the [WindowMode] recorded into, so the window would
+	/// have to be rebuilt rather than reused
+	pub fn requires_rebuild(&self, other: &Settings) -> bool {
+		self.buffer_size != other.buffer_size
+			|| self.buffer_span_duration != other.buffer_span_duration
+			|| self.window_mode != other.window_mode
+	}
+
+	/// The error rate percentage above which the circuit opens
+	pub fn trip_threshold(&self) -> f32 {
+		match self.threshold_mode {
+			ThresholdMode::Absolute => self.error_threshold,
+			ThresholdMode::RelativeToBaseline { baseline, margin } => baseline + margin,
+		}
+	}
+
+	/// Check if `error_rate` trips the circuit, comparing with `>` or with `>=`
+	/// if `inclusive_threshold` is set
+	pub fn trips_at(&self, error_rate: f32) -> bool {
+		if self.inclusive_threshold {
+			error_rate >= self.trip_threshold()
+		} else {
+			error_rate > self.trip_threshold()
 		}
 	}
 }
 
+/// Parse a duration with a `ms`, `s`, `m` or `h` suffix, e.g. `"250ms"` or
+/// `"5m"`. A number without a suffix is read as seconds. The number may have a
+/// fractional part, e.g. `"1.5"` for 1500ms, which is kept down to nanoseconds.
+pub fn parse_duration(input: &str) -> Option<Duration> {
+	let input = input.trim();
+	let (number, unit) = input.split_at(input.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(input.len()));
+	let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+	if !fraction.chars().all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+	let nanos: u32 = format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse().ok()?;
+	let value = Duration::new(whole.parse().ok()?, nanos);
+
+	match unit {
+		"ms" => value.checked_div(1000),
+		"" | "s" => Some(value),
+		"m" => value.checked_mul(60),
+		"h" => value.checked_mul(3600),
+		_ => None,
+	}
+}
+
 /// The main circuit breaker struct
-#[derive(Debug, PartialEq)]
+///
+/// Its `Debug` output is a compact summary, `{:#?}` or
+/// [CircuitBreaker::debug_full] include all fields with every node of the ring
+/// buffer.
+///
+/// Cloning copies the buffer, state, settings and counters, e.g. to hand a
+/// breaker to a worker thread, but drops all registered hooks and the
+/// `atomic_buffer`. The clone shares the [Clock].
+#[derive(Clone, PartialEq)]
 pub struct CircuitBreaker {
 	/// The ring buffer for storing failures/successes
 	buffer: RingBuffer,
@@ -90,652 +625,4379 @@ pub struct CircuitBreaker {
 	state: State,
 	/// The last time we recorded something. Used for time-based advancement
 	last_record: Instant,
+	/// The last time the circuit closed after being half-open
+	closed_at: Option<Instant>,
+	/// The last time an event was actually recorded
+	last_event: Instant,
 	/// The time when we started (useful for resetting, etc.)
 	start_time: Instant,
-	/// Consecutive successes when in HalfOpen state
+	/// The events recorded into the current span, used in
+	/// `WindowMode::SpanEvents`
+	span_event_count: usize,
+	/// Successes when in HalfOpen state. These are consecutive unless
+	/// `Settings.recover_on_error_rate` counts failures alongside them in
+	/// `trial_failure`.
 	trial_success: usize,
+	/// Failures when in HalfOpen state, only counted with
+	/// `Settings.recover_on_error_rate`
+	trial_failure: usize,
+	/// Consecutive failures when in Closed state, used with
+	/// `Settings.trip_on_consecutive`
+	consecutive_failures: usize,
+	/// Why the circuit opened most recently
+	open_reason: Option<OpenReason>,
+	/// The error rate from before an idle gap cleared the ring buffer, used for
+	/// the next evaluation with `Settings.sticky_after_idle`
+	sticky_error_rate: Option<f32>,
+	/// All successes recorded since creation
+	lifetime_successes: usize,
+	/// All failures recorded since creation
+	lifetime_failures: usize,
+	/// When we entered the current state
+	state_since: Instant,
+	/// The time spent in each state, excluding the current state's time
+	state_durations: StateDurations,
+	/// The transitions into each state
+	state_entry_counts: StateEntryCounts,
+	/// The rolling log we record into when in `WindowMode::RollingLog`
+	rolling_log: Option<RollingLog>,
+	/// The buffer half-open probes are recorded into with
+	/// `Settings.half_open_buffer_size`, one probe per node
+	probe_buffer: Option<RingBuffer>,
+	/// The counters other threads record into lock-free, see `atomic_buffer`
+	atomic_buffer: SharedAtomicBuffer,
+	/// The `(failures, successes)` recorded per label via `record_labeled`
+	labels: HashMap<String, (usize, usize)>,
 	/// All relevant circuit-breaker settings in one struct
 	settings: Settings,
+	/// The most recent state transitions, oldest first
+	transitions: Vec<TransitionRecord>,
+	/// The callbacks fired on state transitions
+	hooks: Hooks,
+	/// The source of time for spans, timeouts and transition records
+	clock: BoxedClock,
+	/// The counters accumulated since the last `drain_metrics`
+	metrics: MetricsSnapshot,
+	/// When `pause` was called, `None` unless paused
+	paused_at: Option<Instant>,
+	/// When the state was last evaluated, for `Settings.eval_debounce`
+	last_evaluated_at: Option<Instant>,
+	/// When the last half-open trial success was recorded or last decayed, for
+	/// `Settings.trial_decay_interval`
+	trial_decayed_at: Option<Instant>,
+	/// The name identifying this breaker in its output, empty if unnamed
+	name: String,
 }
 
 impl CircuitBreaker {
+	/// The number of events a single `record_fraction` call is spread over
+	pub const FRACTION_EVENTS: usize = 10;
+
 	/// Create a new [CircuitBreaker] with [Settings]
 	pub fn new(settings: Settings) -> Self {
+		Self::new_with_clock(settings, SystemClock)
+	}
+
+	/// Create a new [CircuitBreaker] with [Settings] reading the time from
+	/// `clock`. With a [ManualClock](crate::ManualClock) replaying the same
+	/// events and clock advances always produces the same breaker.
+	pub fn new_with_clock(settings: Settings, clock: impl Clock + 'static) -> Self {
+		let now = clock.now();
 		Self {
 			buffer: RingBuffer::new(settings.buffer_size),
 			state: State::Closed,
-			last_record: Instant::now(),
-			start_time: Instant::now(),
+			last_record: now,
+			last_event: now,
+			closed_at: None,
+			start_time: now,
+			span_event_count: 0,
 			trial_success: 0,
+			trial_failure: 0,
+			consecutive_failures: 0,
+			open_reason: None,
+			sticky_error_rate: None,
+			lifetime_successes: 0,
+			lifetime_failures: 0,
+			state_since: now,
+			state_durations: StateDurations::default(),
+			state_entry_counts: StateEntryCounts::default(),
+			rolling_log: Self::new_rolling_log(&settings),
+			probe_buffer: None,
+			atomic_buffer: SharedAtomicBuffer::default(),
+			labels: HashMap::new(),
 			settings,
+			transitions: Vec::with_capacity(settings.transition_log_size),
+			hooks: Hooks::default(),
+			clock: BoxedClock::new(clock),
+			metrics: MetricsSnapshot::default(),
+			paused_at: None,
+			last_evaluated_at: None,
+			trial_decayed_at: None,
+			name: String::new(),
+		}
+	}
+
+	/// Name the [CircuitBreaker] to tell it apart from others in its `Debug`
+	/// output and `summary_json`, e.g. when logging many breakers
+	pub fn with_name(mut self, name: String) -> Self {
+		self.name = name;
+		self
+	}
+
+	/// Get the name set via `with_name`, empty if unnamed
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Create a new [CircuitBreaker] with [Settings] or an error if the settings
+	/// are invalid instead of panicking
+	pub fn try_new(settings: Settings) -> Result<Self, SettingsError> {
+		settings.validate()?;
+		Ok(Self::new(settings))
+	}
+
+	fn new_rolling_log(settings: &Settings) -> Option<RollingLog> {
+		match settings.window_mode {
+			WindowMode::Spans | WindowMode::SpanEvents(_) => None,
+			WindowMode::RollingLog(capacity) => Some(RollingLog::new(capacity)),
+		}
+	}
+
+	/// The [Window] we currently record into and evaluate
+	fn window(&self) -> &dyn Window {
+		match &self.rolling_log {
+			Some(log) => log,
+			None => &self.buffer,
+		}
+	}
+
+	fn window_mut(&mut self) -> &mut dyn Window {
+		match &mut self.rolling_log {
+			Some(log) => log,
+			None => &mut self.buffer,
 		}
 	}
 
 	/// Get the current state, possibly updating it first if in Open or Closed
 	pub fn get_state(&mut self) -> State {
-		if let State::Open(_) | State::Closed = self.state {
-			self.evaluate_state();
+		let now = self.clock.now();
+		if matches!(self.state, State::Open(_) | State::Closed) && !self.is_debounced(now) {
+			self.evaluate_now(now);
 		}
 
 		self.state
 	}
 
+	/// Evaluate the state and get it as a single byte, e.g. for a binary status
+	/// protocol: 0 for `Closed`, 1 for `HalfOpen` and 2 for `Open`. See
+	/// [State::from_u8] for the receiving side.
+	pub fn quantized_state(&mut self) -> u8 {
+		self.get_state().severity()
+	}
+
+	/// Get the last known state without evaluating it or advancing the ring
+	/// buffer, e.g. for read-only dashboards. The state may be stale: an open
+	/// circuit past its retry timeout is still reported as open until the next
+	/// `get_state` or `record`.
+	pub fn peek_state(&self) -> State {
+		self.state
+	}
+
 	/// Determine if we need to advance the ring buffer based on how much time has
 	/// passed since `self.last_record`
 	pub fn advance_buffer_for_time(&mut self, now: Instant) {
+		if self.is_paused() {
+			return;
+		}
+		if let WindowMode::SpanEvents(_) = self.settings.window_mode {
+			// spans advance by event count instead
+			return;
+		}
+
 		let elapsed = now.duration_since(self.last_record);
 		if elapsed.is_zero() {
 			return;
 		}
 
-		let spans_elapsed = elapsed.as_nanos().checked_div(self.settings.buffer_span_duration.as_nanos()).unwrap_or(0);
+		let span_nanos = self.settings.buffer_span_duration.as_nanos();
+		let spans_elapsed = elapsed.as_nanos().checked_div(span_nanos).unwrap_or(0);
 		if spans_elapsed > 0 {
-			self.buffer.advance(spans_elapsed as usize);
-			self.last_record = now;
+			// events other threads recorded belong to the spans before we advance
+			self.take_atomic_counts(now);
+			let steps = usize::try_from(spans_elapsed).unwrap_or(usize::MAX);
+			let closed_with_events =
+				self.state == State::Closed && self.rolling_log.is_none() && !self.buffer.has_no_events();
+			let error_rate_before = (closed_with_events && self.settings.sticky_after_idle).then(|| self.get_error_rate());
+
+			// a huge idle gap clears the whole buffer anyway so saturating is safe
+			let advanced = self.buffer.advance(steps);
+			if let Some(atomic_buffer) = self.atomic_buffer.get() {
+				atomic_buffer.advance(steps);
+			}
+			// move by whole spans only so the remainder carries into the next span
+			let remainder = elapsed.as_nanos().checked_rem(span_nanos).unwrap_or(0);
+			self.last_record = now.checked_sub(Duration::from_nanos(u64::try_from(remainder).unwrap_or(0))).unwrap_or(now);
+
+			if closed_with_events && advanced.cleared_all {
+				self.sticky_error_rate = error_rate_before;
+				self.hooks.emit_idle_reset();
+			}
+		}
+	}
+
+	/// Check if a call should be made, which is the case unless the circuit is
+	/// open. In `Settings.shadow` mode or with `OpenPolicy::FailOpen` calls are
+	/// always permitted.
+	///
+	/// This is only a check, calls rejected by `call`, `guard` or `acquire` are
+	/// counted in `MetricsSnapshot.rejected_count` instead.
+	pub fn is_call_permitted(&mut self) -> bool {
+		self.settings.shadow
+			|| self.settings.open_policy == OpenPolicy::FailOpen
+			|| !matches!(self.get_state(), State::Open(_))
+	}
+
+	/// Check if a call is permitted and count it as rejected otherwise
+	fn admit_call(&mut self) -> bool {
+		let permitted = self.is_call_permitted();
+		if !permitted {
+			self.metrics.rejected_count = self.metrics.rejected_count.saturating_add(1);
+		}
+		permitted
+	}
+
+	/// Run `f` if a call is permitted and record its result
+	pub fn call<T, E, F: FnOnce() -> Result<T, E>>(&mut self, f: F) -> Result<T, CircuitError<E>> {
+		if !self.admit_call() {
+			return Err(CircuitError::Open);
+		}
+
+		match f() {
+			Ok(value) => {
+				self.record_bool(true);
+				Ok(value)
+			},
+			Err(error) => {
+				self.record_bool(false);
+				Err(CircuitError::Inner(error))
+			},
 		}
 	}
 
 	/// Record the result of a request: either as a success or failure
 	pub fn record<T, E>(&mut self, input: Result<T, E>) {
+		self.record_bool(input.is_ok());
+	}
+
+	/// Record a single successful or failed request from a success flag, without
+	/// constructing a `Result`. All single-event recording funnels into this so
+	/// the generic variants stay small.
+	pub fn record_bool(&mut self, success: bool) {
+		self.record_outcome(if success { Outcome::Success } else { Outcome::Failure });
+	}
+
+	/// Record a request that timed out. It counts as a failure for the error rate
+	/// and is tallied separately in the ring buffer, see `get_timeout_count`.
+	pub fn record_timeout(&mut self) {
+		self.record_outcome(Outcome::Timeout);
+	}
+
+	/// Record the result of a request where an `Err` only counts as a failure if
+	/// `should_count` returns true for it, otherwise it's ignored like
+	/// `Outcome::Ignore`
+	pub fn record_err_if<T, E, P: FnOnce(&E) -> bool>(&mut self, result: Result<T, E>, should_count: P) {
+		let outcome = match result {
+			Ok(_) => Outcome::Success,
+			Err(error) if should_count(&error) => Outcome::Failure,
+			Err(_) => Outcome::Ignore,
+		};
+		self.record_outcome(outcome);
+	}
+
+	/// Record a request that was already classified by the caller, without the
+	/// `Result` generics of `record`
+	pub fn record_outcome(&mut self, outcome: Outcome) {
+		match outcome {
+			Outcome::Success => self.record_event(true, false, None),
+			Outcome::Failure => self.record_event(false, false, None),
+			Outcome::Timeout => self.record_event(false, true, None),
+			Outcome::Ignore => {},
+		}
+	}
+
+	/// Record the result of a request together with how long it took. The
+	/// latency is tallied per span for `latency_distribution`, except in
+	/// `WindowMode::RollingLog`.
+	pub fn record_timed<T, E>(&mut self, input: Result<T, E>, latency: Duration) {
+		self.record_event(input.is_ok(), false, Some(latency));
+	}
+
+	fn record_event(&mut self, success: bool, timeout: bool, latency: Option<Duration>) {
+		if self.is_paused() {
+			return;
+		}
 		if let State::Open(_) | State::Closed = self.state {
 			self.evaluate_state();
 		}
+		if !matches!(self.state, State::Open(_)) {
+			if success {
+				self.count_recorded(1, 0);
+			} else {
+				self.count_recorded(0, 1);
+			}
+		}
 
 		match self.state {
 			State::Open(_) => {
 				// We do not record anything if the circuit is open
 			},
 			State::HalfOpen => {
-				if input.is_ok() {
-					self.trial_success = self.trial_success.saturating_add(1);
+				if success {
+					self.add_trial_successes(1);
+					self.record_probes(true, 1);
 					self.evaluate_state();
 				} else {
-					self.state = State::Open(Instant::now());
-					self.trial_success = 0;
+					self.record_trial_failures(1);
 				}
 			},
 			State::Closed => {
-				self.advance_buffer_for_time(Instant::now());
-				if input.is_ok() {
-					self.buffer.add_success();
-				} else {
-					self.buffer.add_failure();
+				self.add_to_window(success, 1, self.clock.now());
+				if self.rolling_log.is_none() {
+					if timeout {
+						self.buffer.tally_timeouts(1);
+					}
+					if let Some(latency) = latency {
+						self.buffer.add_latency(latency);
+					}
+				}
+				self.track_consecutive_failures(success, 1);
+				if !success {
+					self.reopen_if_false_recovery();
 				}
 			},
 		}
 	}
 
-	/// Evaluate and possibly transition the state machine
-	pub fn evaluate_state(&mut self) {
-		match self.state {
-			State::Open(opened_at) => {
-				if opened_at.elapsed() >= self.settings.retry_timeout {
-					self.state = State::HalfOpen;
-				}
-			},
-			State::Closed => {
-				self.advance_buffer_for_time(Instant::now());
-				if self.buffer.get_error_rate(self.settings.min_eval_size) > self.settings.error_threshold {
-					self.state = State::Open(Instant::now());
-				}
-			},
-			State::HalfOpen => {
-				if self.trial_success >= self.settings.trial_success_required {
-					self.trial_success = 0;
-					self.state = State::Closed;
-					// TODO: keep data for more granular error detection
-					self.buffer = RingBuffer::new(self.settings.buffer_size);
-					self.last_record = Instant::now();
-					self.start_time = Instant::now();
-				}
-			},
+	/// Record the result of a request only if the circuit is closed and return
+	/// whether it was counted. Open and half-open circuits are left untouched,
+	/// so these events never influence the trial successes.
+	pub fn record_if_closed<T, E>(&mut self, input: Result<T, E>) -> bool {
+		if self.get_state() != State::Closed {
+			return false;
 		}
+
+		self.record_bool(input.is_ok());
+		true
 	}
 
-	/// Get the ring buffer instance as mutable reference
-	pub fn get_buffer(&mut self) -> &mut RingBuffer {
-		&mut self.buffer
+	/// Update the time of the last event and the counters for recorded events
+	fn count_recorded(&mut self, successes: usize, failures: usize) {
+		self.last_event = self.clock.now();
+		self.metrics.events_recorded = self.metrics.events_recorded.saturating_add(successes.saturating_add(failures));
+		self.lifetime_successes = self.lifetime_successes.saturating_add(successes);
+		self.lifetime_failures = self.lifetime_failures.saturating_add(failures);
+		self.hooks.emit_record(successes, failures);
 	}
 
-	/// Get the number of successes we have recorded in HalfOpen state
-	pub fn get_trial_success(&self) -> usize {
-		self.trial_success
+	/// Add `count` events to the [Window] after advancing the ring buffer to the
+	/// span for `at` or, in `WindowMode::SpanEvents`, by the number of events in
+	/// the span
+	fn add_to_window(&mut self, success: bool, count: usize, at: Instant) {
+		self.advance_buffer_for_time(at);
+
+		let mut remaining = count;
+		while remaining > 0 {
+			let chunk = match self.settings.window_mode {
+				WindowMode::SpanEvents(span_events) => {
+					let span_events = span_events.max(1);
+					if self.span_event_count >= span_events {
+						self.buffer.advance(1);
+						self.span_event_count = 0;
+					}
+					remaining.min(span_events.saturating_sub(self.span_event_count))
+				},
+				_ => remaining,
+			};
+
+			if success {
+				self.window_mut().add_successes(chunk);
+			} else {
+				self.window_mut().add_failures(chunk);
+			}
+			self.span_event_count = self.span_event_count.saturating_add(chunk);
+			remaining = remaining.saturating_sub(chunk);
+		}
 	}
 
-	/// Get our [Settings]
-	pub fn get_settings(&self) -> &Settings {
-		&self.settings
+	/// Record a batch of results that all belong to the span for `at`, e.g. to
+	/// import historical outcomes bucketed by time. The ring buffer advances to
+	/// that span once and all results are recorded into it.
+	///
+	/// The buffer never moves back or ahead of the clock, so an `at` before the
+	/// current span is recorded into the current span and an `at` in the future
+	/// is recorded as of now.
+	///
+	/// Unless the circuit is closed the results are recorded one by one as with
+	/// `record`.
+	pub fn record_window<T, E>(&mut self, at: Instant, results: impl IntoIterator<Item = Result<T, E>>) {
+		if self.is_paused() {
+			return;
+		}
+		if let State::Open(_) | State::Closed = self.state {
+			self.evaluate_state();
+		}
+		if self.state != State::Closed {
+			for result in results {
+				self.record_bool(result.is_ok());
+			}
+			return;
+		}
+
+		let (successes, failures) = results.into_iter().fold((0_usize, 0_usize), |(successes, failures), result| {
+			if result.is_ok() {
+				(successes.saturating_add(1), failures)
+			} else {
+				(successes, failures.saturating_add(1))
+			}
+		});
+		if successes.saturating_add(failures) == 0 {
+			return;
+		}
+
+		let at = at.clamp(self.last_record, self.clock.now().max(self.last_record));
+		self.count_recorded(successes, failures);
+		self.add_to_window(true, successes, at);
+		self.add_to_window(false, failures, at);
+		if failures > 0 {
+			self.reopen_if_false_recovery();
+		}
 	}
 
-	/// Get the error rate calculated for the ring buffer thus far
-	pub fn get_error_rate(&self) -> f32 {
-		self.buffer.get_error_rate(self.settings.min_eval_size)
+	/// Get how long the circuit stays open before moving to half-open. This
+	/// currently equals `Settings.retry_timeout` and exists so UIs, e.g. the
+	/// visualizer countdown, have a single source once backoff lands.
+	pub fn effective_retry_timeout(&self) -> Duration {
+		self.settings.retry_timeout
 	}
 
-	/// Get the elapsed time of our current phase
-	pub fn get_elapsed_time(&self, buffer_span_duration: Duration, now: Instant) -> Duration {
-		let elapsed = now.duration_since(self.start_time);
-		let remainder_ns = elapsed.as_nanos().checked_rem(buffer_span_duration.as_nanos()).unwrap_or(u128::MAX);
-		Duration::from_nanos(remainder_ns as u64)
+	/// Get when the circuit moves to half-open and the next probe call can be
+	/// attempted or `None` if the circuit isn't open
+	pub fn next_probe_at(&self) -> Option<Instant> {
+		match self.state {
+			State::Open(opened_at) => opened_at.checked_add(self.effective_retry_timeout()),
+			State::Closed | State::HalfOpen => None,
+		}
 	}
-}
 
-impl Default for CircuitBreaker {
-	fn default() -> Self {
-		Self::new(Settings::default())
+	/// Get the time left until an open circuit moves to half-open or `None` if
+	/// the circuit isn't open
+	pub fn time_until_retry(&self) -> Option<Duration> {
+		self.next_probe_at().map(|probe_at| probe_at.saturating_duration_since(self.clock.now()))
 	}
-}
 
-#[cfg(test)]
-mod test {
-	use super::*;
-	use crate::ring_buffer::NodeInfo;
+	/// Get the time left until retrying in whole seconds, rounded up so clients
+	/// don't retry too early, e.g. for an HTTP `Retry-After` header. Returns
+	/// `None` if the circuit isn't open.
+	pub fn retry_after_secs(&self) -> Option<u64> {
+		self.time_until_retry().map(|remaining| {
+			let secs = remaining.as_secs();
+			if remaining.subsec_nanos() > 0 {
+				secs.saturating_add(1)
+			} else {
+				secs
+			}
+		})
+	}
 
-	#[test]
-	fn state_fmt_test() {
-		assert_eq!(format!("{}", State::Open(Instant::now())), String::from("\x1b[41m Open \x1b[0m     "));
-		assert_eq!(format!("{:#}", State::Open(Instant::now())), String::from("\x1b[0m─"));
-		assert_eq!(format!("{}", State::Closed), String::from("Closed     "));
-		assert_eq!(format!("{:#}", State::Closed), String::from("│"));
-		assert_eq!(format!("{}", State::HalfOpen), String::from("\x1b[43m Half Open \x1b[0m"));
-		assert_eq!(format!("{:#}", State::HalfOpen), String::from("/"));
+	/// Check if a probe call should be made now, moving an open circuit to
+	/// half-open once `next_probe_at` has passed
+	pub fn attempt_probe(&mut self) -> bool {
+		self.get_state() == State::HalfOpen
 	}
 
-	#[test]
-	fn new_test() {
-		assert_eq!(CircuitBreaker::new(Settings::default()).buffer.get_size(), 5);
-		assert_eq!(CircuitBreaker::new(Settings::default()).settings, Settings::default());
-		assert_eq!(
-			CircuitBreaker::new(Settings {
-				buffer_size: 10,
+	/// Run `f` as a probe call if one should be made now, see `attempt_probe`,
+	/// record its result and report whether it closed the circuit. `f` isn't
+	/// run unless the circuit is half-open.
+	pub fn probe<T, E, F: FnOnce() -> Result<T, E>>(&mut self, f: F) -> ProbeResult<T, E> {
+		if !self.attempt_probe() {
+			return ProbeResult {
+				ran: false,
+				closed: false,
+				result: None,
+			};
+		}
+
+		let result = f();
+		self.record_bool(result.is_ok());
+		ProbeResult {
+			ran: true,
+			closed: self.state == State::Closed,
+			result: Some(result),
+		}
+	}
+
+	/// Count consecutive failures in Closed state and evaluate the state once
+	/// `Settings.trip_on_consecutive` is reached
+	fn track_consecutive_failures(&mut self, success: bool, count: usize) {
+		if success {
+			if count > 0 {
+				self.consecutive_failures = 0;
+			}
+			return;
+		}
+
+		self.consecutive_failures = self.consecutive_failures.saturating_add(count);
+		if self.consecutive_failures_reached() {
+			self.evaluate_state();
+		}
+	}
+
+	/// Open a closed circuit again after a failure within
+	/// `Settings.post_recovery_sensitive` of closing after a half-open trial
+	fn reopen_if_false_recovery(&mut self) {
+		let now = self.clock.now();
+		let sensitive = self
+			.closed_at
+			.is_some_and(|closed_at| now.saturating_duration_since(closed_at) < self.settings.post_recovery_sensitive);
+		if self.state == State::Closed && sensitive {
+			self.open(OpenReason::TrialFailed, now);
+		}
+	}
+
+	fn consecutive_failures_reached(&self) -> bool {
+		self.settings.trip_on_consecutive.is_some_and(|trip_on| self.consecutive_failures >= trip_on)
+	}
+
+	/// Check if the approximate p95 latency of at least `min_eval_size` timed
+	/// calls exceeds `Settings.p95_slow_threshold`
+	fn slow_calls_reached(&self) -> bool {
+		let timed_calls = self.latency_distribution().iter().fold(0_usize, |total, count| total.saturating_add(*count));
+		self.settings.p95_slow_threshold.is_some_and(|threshold| {
+			timed_calls >= self.settings.min_eval_size && self.approx_p95().is_some_and(|p95| p95 > threshold)
+		})
+	}
+
+	/// Open the circuit at `now` and remember why
+	fn open(&mut self, reason: OpenReason, now: Instant) {
+		self.open_reason = Some(reason);
+		self.consecutive_failures = 0;
+		self.transition_to(State::Open(now), now);
+	}
+
+	/// Get why the circuit opened most recently or `None` if it never opened
+	pub fn get_open_reason(&self) -> Option<OpenReason> {
+		self.open_reason
+	}
+
+	/// Get a [CallGuard] that records the outcome of a call once dropped or
+	/// `None` if the circuit is open and the call should not be made.
+	///
+	/// The guard mutably borrows the [CircuitBreaker] until it is dropped.
+	pub fn guard(&mut self) -> Option<CallGuard<'_>> {
+		if self.admit_call() {
+			Some(CallGuard::new(self))
+		} else {
+			None
+		}
+	}
+
+	/// Get a [Permit] for a call which records its outcome once finalized or
+	/// dropped, or [Rejected] with the time until retrying if the circuit is
+	/// open, so the check and the recording can't diverge.
+	pub fn acquire(&mut self) -> Result<Permit<'_>, Rejected> {
+		if self.admit_call() {
+			Ok(Permit::new(self))
+		} else {
+			Err(Rejected {
+				retry_after: self.time_until_retry().unwrap_or_default(),
+			})
+		}
+	}
+
+	/// Record the result of a request and tally it for `label` as well.
+	///
+	/// The circuit still evaluates the aggregate of all labels, the per label
+	/// tallies are for insight only and can be read via `label_error_rate`.
+	pub fn record_labeled<T, E>(&mut self, label: &str, input: Result<T, E>) {
+		let success = input.is_ok();
+		if !matches!(self.get_state(), State::Open(_)) {
+			let tally = self.labels.entry(label.to_string()).or_insert((0, 0));
+			if success {
+				tally.1 = tally.1.saturating_add(1);
+			} else {
+				tally.0 = tally.0.saturating_add(1);
+			}
+		}
+
+		self.record_bool(success);
+	}
+
+	/// Get the error rate of all requests recorded for `label` or `None` if
+	/// nothing was recorded for it
+	pub fn label_error_rate(&self, label: &str) -> Option<f32> {
+		self.labels.get(label).map(|(failures, successes)| percentage(*failures, failures.saturating_add(*successes)))
+	}
+
+	/// Record a single request counting as `weight` requests, e.g. to count the
+	/// failures of a critical endpoint double. The weight scales both the
+	/// failures or successes and the total the error rate is divided by, so a
+	/// failure of weight 2 next to 2 successes reads 50%.
+	///
+	/// Only the [Window] is weighted: the request still counts once for the
+	/// half-open trials, `Settings.trip_on_consecutive`, the `on_record` hooks
+	/// and the lifetime totals. A weight of 0 records nothing.
+	pub fn record_with_weight(&mut self, success: bool, weight: usize) {
+		if weight == 0 || self.is_paused() {
+			return;
+		}
+		if let State::Open(_) | State::Closed = self.state {
+			self.evaluate_state();
+		}
+		if self.state != State::Closed {
+			self.record_bool(success);
+			return;
+		}
+
+		if success {
+			self.count_recorded(1, 0);
+		} else {
+			self.count_recorded(0, 1);
+		}
+		self.add_to_window(success, weight, self.clock.now());
+		self.track_consecutive_failures(success, 1);
+		if !success {
+			self.reopen_if_false_recovery();
+		}
+	}
+
+	/// Record `count` successful requests at once, e.g. to warm up a freshly
+	/// created [CircuitBreaker] with known-good traffic
+	pub fn record_many_successes(&mut self, count: usize) {
+		if count == 0 || self.is_paused() {
+			return;
+		}
+		if let State::Open(_) | State::Closed = self.state {
+			self.evaluate_state();
+		}
+		if !matches!(self.state, State::Open(_)) {
+			self.count_recorded(count, 0);
+		}
+
+		match self.state {
+			State::Open(_) => {
+				// We do not record anything if the circuit is open
+			},
+			State::HalfOpen => {
+				self.add_trial_successes(count);
+				self.record_probes(true, count);
+				self.evaluate_state();
+			},
+			State::Closed => {
+				self.add_to_window(true, count, self.clock.now());
+				self.track_consecutive_failures(true, count);
+			},
+		}
+	}
+
+	/// Record `count` failed requests at once
+	pub fn record_many_failures(&mut self, count: usize) {
+		if count == 0 || self.is_paused() {
+			return;
+		}
+
+		if let State::Open(_) | State::Closed = self.state {
+			self.evaluate_state();
+		}
+		if !matches!(self.state, State::Open(_)) {
+			self.count_recorded(0, count);
+		}
+
+		match self.state {
+			State::Open(_) => {
+				// We do not record anything if the circuit is open
+			},
+			State::HalfOpen => {
+				self.record_trial_failures(count);
+			},
+			State::Closed => {
+				self.add_to_window(false, count, self.clock.now());
+				self.track_consecutive_failures(false, count);
+				self.reopen_if_false_recovery();
+			},
+		}
+	}
+
+	/// Count `count` half-open trial successes after decaying the stale ones
+	fn add_trial_successes(&mut self, count: usize) {
+		let now = self.clock.now();
+		self.decay_trial_success(now);
+		self.trial_success = self.trial_success.saturating_add(count);
+		self.trial_decayed_at = Some(now);
+	}
+
+	/// Take back one trial success per `Settings.trial_decay_interval` elapsed
+	/// since the last success or decay. The remainder carries over to the next
+	/// interval.
+	fn decay_trial_success(&mut self, now: Instant) {
+		let (Some(interval), Some(decayed_at)) = (self.settings.trial_decay_interval, self.trial_decayed_at) else {
+			return;
+		};
+		let elapsed = now.saturating_duration_since(decayed_at);
+		let decays = elapsed.as_nanos().checked_div(interval.as_nanos()).unwrap_or(0);
+		if decays == 0 {
+			return;
+		}
+
+		self.trial_success = self.trial_success.saturating_sub(usize::try_from(decays).unwrap_or(usize::MAX));
+		let remainder = elapsed.as_nanos().checked_rem(interval.as_nanos()).unwrap_or(0);
+		self.trial_decayed_at =
+			Some(now.checked_sub(Duration::from_nanos(u64::try_from(remainder).unwrap_or(0))).unwrap_or(now));
+	}
+
+	/// Record failures in HalfOpen state which open the circuit right away
+	/// unless we recover by error rate
+	fn record_trial_failures(&mut self, count: usize) {
+		if self.probe_buffer.is_some() {
+			self.record_probes(false, count);
+			self.evaluate_state();
+		} else if self.settings.recover_on_error_rate.is_some() {
+			self.trial_failure = self.trial_failure.saturating_add(count);
+			self.evaluate_state();
+		} else {
+			self.reopen(self.clock.now());
+		}
+	}
+
+	/// Record `count` probes into the probe buffer, if any, each into its own
+	/// node. Only the last probes which fit into the buffer are kept.
+	fn record_probes(&mut self, success: bool, count: usize) {
+		if let Some(probe_buffer) = &mut self.probe_buffer {
+			for _ in 0..count.min(probe_buffer.get_size()) {
+				probe_buffer.advance(1);
+				if success {
+					probe_buffer.add_success();
+				} else {
+					probe_buffer.add_failure();
+				}
+			}
+		}
+	}
+
+	/// Open a half-open circuit again and drop the trial progress
+	fn reopen(&mut self, now: Instant) {
+		self.open(OpenReason::TrialFailed, now);
+		self.trial_success = 0;
+		self.trial_failure = 0;
+	}
+
+	/// Record a partially successful request, e.g. a batch call where 8 of 10
+	/// items succeeded, as `success_ratio` from 0.0 to 1.0.
+	///
+	/// The ratio is spread over `Self::FRACTION_EVENTS` whole events: the ratio
+	/// rounded to the nearest tenth are recorded as successes and the rest as
+	/// failures, so a ratio of 0.8 records 8 successes and 2 failures. Each call
+	/// therefore counts as 10 events towards `min_eval_size`. Ratios outside the
+	/// range are clamped and NaN counts as a complete failure.
+	///
+	/// The failures are recorded first, so a half-open circuit can't close on
+	/// the successes of a partially failed request.
+	pub fn record_fraction(&mut self, success_ratio: f32) {
+		let ratio = if success_ratio.is_nan() {
+			0.0
+		} else {
+			success_ratio.clamp(0.0, 1.0)
+		};
+		let successes = (ratio * Self::FRACTION_EVENTS as f32).round() as usize;
+
+		self.record_many_failures(Self::FRACTION_EVENTS.saturating_sub(successes));
+		self.record_many_successes(successes);
+	}
+
+	/// Register a hook that fires exactly once per open episode, on the edge
+	/// from Closed or HalfOpen to Open, no matter how often the state is checked
+	/// while the circuit stays open
+	pub fn on_open_emit_once(&mut self, hook: impl FnMut() + Send + 'static) {
+		self.hooks.add_on_open(hook);
+	}
+
+	/// Register a hook that fires when a long idle gap clears the whole ring
+	/// buffer while the circuit is closed
+	pub fn on_idle_reset(&mut self, hook: impl FnMut() + Send + 'static) {
+		self.hooks.add_on_idle_reset(hook);
+	}
+
+	/// Register a hook that fires on every state transition with the `(from,
+	/// to)` states
+	pub fn on_transition(&mut self, hook: impl FnMut(State, State) + Send + 'static) {
+		self.hooks.add_on_transition(hook);
+	}
+
+	/// Register a hook that fires whenever events are counted, with the
+	/// `(successes, failures)` recorded at once. Events dropped while the
+	/// circuit is open aren't counted.
+	pub fn on_record(&mut self, hook: impl FnMut(usize, usize) + Send + 'static) {
+		self.hooks.add_on_record(hook);
+	}
+
+	/// Move to `state` at `now` and fire the hooks for this transition
+	fn transition_to(&mut self, state: State, now: Instant) {
+		let was_open = matches!(self.state, State::Open(_));
+		let from = self.state;
+		self.state = state;
+		self.metrics.transition_count = self.metrics.transition_count.saturating_add(1);
+
+		self.state_durations.add(from, now.saturating_duration_since(self.state_since));
+		self.state_since = now;
+		let entries = match state {
+			State::Closed => &mut self.state_entry_counts.closed,
+			State::Open(_) => &mut self.state_entry_counts.open,
+			State::HalfOpen => &mut self.state_entry_counts.half_open,
+		};
+		*entries = entries.saturating_add(1);
+
+		if from == State::HalfOpen && state == State::Closed {
+			self.closed_at = Some(now);
+		}
+		self.probe_buffer = match state {
+			State::HalfOpen => self.settings.half_open_buffer_size.map(|size| RingBuffer::new(size.max(1))),
+			_ => None,
+		};
+
+		if self.settings.transition_log_size > 0 {
+			if self.transitions.len() >= self.settings.transition_log_size {
+				let excess = self.transitions.len().saturating_sub(self.settings.transition_log_size).saturating_add(1);
+				self.transitions.drain(..excess);
+			}
+			self.transitions.push(TransitionRecord {
+				at: now,
+				from,
+				to: state,
+			});
+		}
+
+		if !was_open && matches!(state, State::Open(_)) {
+			self.hooks.emit_open();
+		}
+		self.hooks.emit_transition(from, state);
+	}
+
+	/// Evaluate and possibly transition the state machine
+	pub fn evaluate_state(&mut self) {
+		self.evaluate_now(self.clock.now());
+	}
+
+	/// Evaluate and possibly transition the state machine as of `now` instead
+	/// of the clock's time, e.g. to drive transitions deterministically in tests
+	pub fn evaluate_now(&mut self, now: Instant) {
+		if self.is_paused() {
+			return;
+		}
+		self.last_evaluated_at = Some(now);
+		self.take_atomic_counts(now);
+		match self.state {
+			State::Open(opened_at) => {
+				if now.saturating_duration_since(opened_at) >= self.effective_retry_timeout() {
+					self.transition_to(State::HalfOpen, now);
+				}
+			},
+			State::Closed => {
+				self.advance_buffer_for_time(now);
+				let cooling_down = self
+					.closed_at
+					.is_some_and(|closed_at| now.saturating_duration_since(closed_at) < self.settings.close_cooldown);
+				let error_rate = self.sticky_error_rate.take().unwrap_or_else(|| self.get_error_rate());
+				// kinds missing from the priority, e.g. listed twice, are still checked last
+				let tripped = self.settings.trip_priority.iter().chain(TripKind::ALL.iter()).find(|kind| match kind {
+					TripKind::ErrorRate => !cooling_down && self.settings.trips_at(error_rate),
+					TripKind::ConsecutiveFailures => self.consecutive_failures_reached(),
+					TripKind::SlowCalls => !cooling_down && self.slow_calls_reached(),
+				});
+				if let Some(kind) = tripped {
+					self.open(OpenReason::Trip(*kind), now);
+				}
+			},
+			State::HalfOpen => {
+				self.decay_trial_success(now);
+				let recovered = match (&self.probe_buffer, self.settings.recover_on_error_rate) {
+					(Some(probe_buffer), _) => {
+						let sample = self.settings.trial_success_required.min(probe_buffer.get_size()).max(1);
+						let probes = probe_buffer.fold_nodes(0_usize, |probes, _, info| probes.saturating_add(info.total()));
+						if probes < sample {
+							return;
+						}
+						if self.settings.trips_at(probe_buffer.get_error_rate_including_current(0)) {
+							self.reopen(now);
+							return;
+						}
+						true
+					},
+					(None, None) => self.trial_success >= self.settings.trial_success_required,
+					(None, Some(recover_threshold)) => {
+						let sample = self.trial_success.saturating_add(self.trial_failure);
+						if sample < self.settings.trial_success_required.max(1) {
+							return;
+						}
+						if percentage(self.trial_failure, sample) >= recover_threshold {
+							self.reopen(now);
+							return;
+						}
+						true
+					},
+				};
+
+				if recovered {
+					self.close(now);
+				}
+			},
+		}
+	}
+
+	/// Get the [AtomicRingBuffer] other threads can record into without locking
+	/// the [CircuitBreaker], e.g. by sharing it across a thread pool while the
+	/// breaker itself stays behind a `Mutex`. It advances along with the ring
+	/// buffer and its counts are merged into the span they were recorded in
+	/// whenever the state is evaluated, e.g. on `get_state`, or the spans
+	/// advance. Clones of the breaker start without an atomic buffer.
+	///
+	/// Only the error rate sees these events: they don't count towards
+	/// `Settings.trip_on_consecutive`, the half-open trials or the `on_record`
+	/// hooks, and events taken while the circuit isn't closed are dropped like
+	/// events recorded into an open circuit. Outside of `WindowMode::Spans` or
+	/// once `Settings.buffer_size` changed they are all added to the current
+	/// span.
+	pub fn atomic_buffer(&mut self) -> Arc<AtomicRingBuffer> {
+		self.atomic_buffer.get_or_create(self.settings.buffer_size)
+	}
+
+	/// Move the counts recorded into the `atomic_buffer` since they were last
+	/// taken into the [Window]
+	fn take_atomic_counts(&mut self, now: Instant) {
+		let Some(atomic_buffer) = self.atomic_buffer.get() else {
+			return;
+		};
+		let taken = atomic_buffer.take();
+		let (failures, successes) = taken.fold_nodes((0_usize, 0_usize), |(failures, successes), _, info| {
+			(failures.saturating_add(info.failure_count), successes.saturating_add(info.success_count))
+		});
+		if self.state != State::Closed || failures.saturating_add(successes) == 0 {
+			return;
+		}
+
+		self.last_event = now;
+		self.metrics.events_recorded = self.metrics.events_recorded.saturating_add(failures.saturating_add(successes));
+		self.lifetime_successes = self.lifetime_successes.saturating_add(successes);
+		self.lifetime_failures = self.lifetime_failures.saturating_add(failures);
+		if self.settings.window_mode != WindowMode::Spans || !self.buffer.merge(&taken) {
+			self.add_to_window(false, failures, now);
+			self.add_to_window(true, successes, now);
+		}
+		if failures > 0 {
+			self.reopen_if_false_recovery();
+		}
+	}
+
+	/// Close the circuit at `now` and start over with an empty [Window]
+	fn close(&mut self, now: Instant) {
+		self.trial_success = 0;
+		self.trial_failure = 0;
+		self.transition_to(State::Closed, now);
+		// TODO: keep data for more granular error detection
+		self.buffer = RingBuffer::new(self.settings.buffer_size);
+		self.rolling_log = Self::new_rolling_log(&self.settings);
+		self.span_event_count = 0;
+		self.last_record = now;
+		self.start_time = now;
+	}
+
+	/// Close the circuit right away if it looks healthy, e.g. as a guarded
+	/// override for operators. A half-open circuit is healthy once it made at
+	/// least one probe and its probes wouldn't open it again. An open circuit
+	/// has no recent data to judge by as recording is paused while open, so it
+	/// is never closed. Otherwise the circuit is left alone and its current
+	/// state is returned as the error.
+	pub fn try_close(&mut self) -> Result<(), State> {
+		let healthy = match self.get_state() {
+			State::Closed => return Ok(()),
+			state @ State::Open(_) => return Err(state),
+			State::HalfOpen => match (&self.probe_buffer, self.settings.recover_on_error_rate) {
+				(Some(probe_buffer), _) => {
+					probe_buffer.fold_nodes(0_usize, |probes, _, info| probes.saturating_add(info.total())) > 0
+						&& !self.settings.trips_at(probe_buffer.get_error_rate_including_current(0))
+				},
+				(None, None) => self.trial_success > 0,
+				(None, Some(recover_threshold)) => {
+					let sample = self.trial_success.saturating_add(self.trial_failure);
+					sample > 0 && percentage(self.trial_failure, sample) < recover_threshold
+				},
+			},
+		};
+
+		if !healthy {
+			return Err(self.state);
+		}
+		self.close(self.clock.now());
+		Ok(())
+	}
+
+	/// Check if the state was evaluated less than `Settings.eval_debounce` before
+	/// `now`
+	fn is_debounced(&self, now: Instant) -> bool {
+		match (self.settings.eval_debounce, self.last_evaluated_at) {
+			(Some(debounce), Some(evaluated_at)) => now.saturating_duration_since(evaluated_at) < debounce,
+			_ => false,
+		}
+	}
+
+	/// Freeze the [CircuitBreaker], e.g. for a maintenance window without
+	/// traffic. While paused the state isn't evaluated, the ring buffer doesn't
+	/// advance and recording is a no-op, so a long pause neither clears the
+	/// buffer nor moves an open circuit to half-open.
+	pub fn pause(&mut self) {
+		if self.is_paused() {
+			return;
+		}
+		// account for the time before the pause
+		self.advance_buffer_for_time(self.clock.now());
+		self.paused_at = Some(self.clock.now());
+	}
+
+	/// Unfreeze a paused [CircuitBreaker]. The paused duration is excluded from
+	/// the current span, the retry timeout, the close cooldown, the
+	/// `state_durations`, `Settings.eval_debounce` and
+	/// `Settings.trial_decay_interval`.
+	pub fn resume(&mut self) {
+		let Some(paused_at) = self.paused_at.take() else {
+			return;
+		};
+
+		let paused = self.clock.now().saturating_duration_since(paused_at);
+		let shift = |instant: Instant| instant.checked_add(paused).unwrap_or(instant);
+		self.last_record = shift(self.last_record);
+		self.start_time = shift(self.start_time);
+		self.closed_at = self.closed_at.map(shift);
+		self.state_since = shift(self.state_since);
+		self.last_evaluated_at = self.last_evaluated_at.map(shift);
+		self.trial_decayed_at = self.trial_decayed_at.map(shift);
+		if let State::Open(opened_at) = self.state {
+			self.state = State::Open(shift(opened_at));
+		}
+	}
+
+	/// Check if the [CircuitBreaker] is paused via `pause`
+	pub fn is_paused(&self) -> bool {
+		self.paused_at.is_some()
+	}
+
+	/// Swap the [Settings] without disturbing the current state, trial progress
+	/// or recorded data. The ring buffer is only resized if `buffer_size` changed
+	/// and the rolling log is only recreated if `window_mode` changed.
+	pub fn replace_settings_preserving_state(&mut self, settings: Settings) {
+		if settings.buffer_size != self.settings.buffer_size {
+			self.buffer.resize(settings.buffer_size);
+		}
+		if settings.window_mode != self.settings.window_mode {
+			self.rolling_log = Self::new_rolling_log(&settings);
+		}
+		self.settings = settings;
+	}
+
+	/// Get the counters accumulated since the last drain and reset them, without
+	/// touching the recorded data or state
+	pub fn drain_metrics(&mut self) -> MetricsSnapshot {
+		std::mem::take(&mut self.metrics)
+	}
+
+	/// Get the debug output of all fields including every node of the ring
+	/// buffer, the same as `{:#?}`
+	pub fn debug_full(&self) -> String {
+		format!("{self:#?}")
+	}
+
+	/// Get the ring buffer instance as mutable reference
+	pub fn get_buffer(&mut self) -> &mut RingBuffer {
+		&mut self.buffer
+	}
+
+	/// Get an independent copy of the ring buffer, e.g. for snapshots
+	pub fn clone_buffer(&self) -> RingBuffer {
+		self.buffer.clone()
+	}
+
+	/// Get the number of events recorded into the current span in
+	/// `WindowMode::SpanEvents`
+	pub fn get_span_event_count(&self) -> usize {
+		self.span_event_count
+	}
+
+	/// Get the last `Settings.transition_log_size` state transitions, oldest
+	/// first
+	pub fn transition_history(&self) -> &[TransitionRecord] {
+		&self.transitions
+	}
+
+	/// Get the total time spent in each state, including the time spent in the
+	/// current state so far
+	pub fn state_durations(&self) -> StateDurations {
+		let mut durations = self.state_durations;
+		durations.add(self.state, self.clock.now().saturating_duration_since(self.state_since));
+		durations
+	}
+
+	/// Get the `(successes, failures)` recorded since creation. Events ignored
+	/// while the circuit is open aren't counted.
+	pub fn lifetime_totals(&self) -> (usize, usize) {
+		(self.lifetime_successes, self.lifetime_failures)
+	}
+
+	/// Add the node counts and lifetime totals of `other` to this breaker,
+	/// e.g. to aggregate per-shard breakers into one for reporting. The state
+	/// is left alone. Returns false and merges nothing if the ring buffers
+	/// differ in size.
+	pub fn merge_metrics_from(&mut self, other: &CircuitBreaker) -> bool {
+		if !self.buffer.merge(&other.buffer) {
+			return false;
+		}
+		self.lifetime_successes = self.lifetime_successes.saturating_add(other.lifetime_successes);
+		self.lifetime_failures = self.lifetime_failures.saturating_add(other.lifetime_failures);
+		true
+	}
+
+	/// Get how often we transitioned into each state. Starting out closed
+	/// doesn't count as an entry.
+	pub fn state_entry_counts(&self) -> StateEntryCounts {
+		self.state_entry_counts
+	}
+
+	/// Get how many more successful probes a half-open circuit needs to close
+	/// or, with `Settings.recover_on_error_rate`, how many events are missing
+	/// from the sample. Outside of HalfOpen state this is all of them.
+	pub fn trial_successes_remaining(&self) -> usize {
+		let progress = match (self.state, self.settings.recover_on_error_rate) {
+			(State::HalfOpen, None) => self.trial_success,
+			(State::HalfOpen, Some(_)) => self.trial_success.saturating_add(self.trial_failure),
+			_ => 0,
+		};
+		self.settings.trial_success_required.saturating_sub(progress)
+	}
+
+	/// Estimate how long it takes to close the circuit if we send
+	/// `probes_per_sec` probes that all succeed. An open circuit first waits for
+	/// its retry timeout. Returns `None` for a rate that isn't positive.
+	pub fn estimate_recovery(&self, probes_per_sec: f32) -> Option<Duration> {
+		if self.state == State::Closed {
+			return Some(Duration::ZERO);
+		}
+		if probes_per_sec.is_nan() || probes_per_sec <= 0.0 {
+			return None;
+		}
+
+		let probing = Duration::try_from_secs_f32(self.trial_successes_remaining() as f32 / probes_per_sec).ok()?;
+		let waiting = match self.next_probe_at() {
+			Some(probe_at) => probe_at.saturating_duration_since(self.clock.now()),
+			None => Duration::ZERO,
+		};
+		Some(waiting.saturating_add(probing))
+	}
+
+	/// Check if no traffic was observed yet, e.g. to tell a freshly started
+	/// [CircuitBreaker] from a healthy one
+	pub fn is_cold(&self) -> bool {
+		self.lifetime_successes == 0 && self.lifetime_failures == 0 && self.buffer.has_no_events()
+	}
+
+	/// Get the number of timeouts recorded via `record_timeout` across the ring
+	/// buffer. Timeouts aren't tallied in `WindowMode::RollingLog`.
+	pub fn get_timeout_count(&self) -> usize {
+		self.buffer.get_timeout_count()
+	}
+
+	/// Get the number of events recorded via `record_timed` across the ring
+	/// buffer per latency bucket: below 50ms, below 200ms, below 1s and 1s or
+	/// more
+	pub fn latency_distribution(&self) -> [usize; 4] {
+		self.buffer.latency_distribution()
+	}
+
+	/// Get a coarse approximation of the p95 latency from the
+	/// `latency_distribution`: the upper bound of the bucket the 95th percentile
+	/// falls into, so 50ms, 200ms, 1s or `Duration::MAX` for 1s or more. `None`
+	/// if no calls were recorded via `record_timed`.
+	pub fn approx_p95(&self) -> Option<Duration> {
+		let distribution = self.latency_distribution();
+		let total = distribution.iter().fold(0_usize, |total, count| total.saturating_add(*count));
+		if total == 0 {
+			return None;
+		}
+
+		// the number of calls at or below the p95, rounded up
+		let rank = total.saturating_mul(95).div_ceil(100);
+		let mut seen = 0_usize;
+		for (bucket, count) in distribution.iter().enumerate() {
+			seen = seen.saturating_add(*count);
+			if seen >= rank {
+				return Some(match LATENCY_BUCKET_BOUNDS_MS.get(bucket) {
+					Some(bound) => Duration::from_millis(*bound as u64),
+					None => Duration::MAX,
+				});
+			}
+		}
+		Some(Duration::MAX)
+	}
+
+	/// Get the number of successes we have recorded in HalfOpen state
+	pub fn get_trial_success(&self) -> usize {
+		self.trial_success
+	}
+
+	/// Get our [Settings]
+	pub fn get_settings(&self) -> &Settings {
+		&self.settings
+	}
+
+	/// Get the error rate calculated for the current [Window] thus far
+	pub fn get_error_rate(&self) -> f32 {
+		if self.rolling_log.is_some() {
+			return self.window().get_error_rate(self.settings.min_eval_size);
+		}
+		if self.settings.evaluate_cursor_when_window_empty && self.buffer.evaluated_events() == 0 {
+			return self.buffer.get_error_rate_including_current(self.settings.min_eval_size);
+		}
+
+		self.buffer.get_error_rate_trailing(
+			self.settings.min_eval_size,
+			self.settings.denominator_policy.spans(),
+			self.settings.include_current_span,
+		)
+	}
+
+	/// Get the number of events `get_error_rate` is calculated from
+	fn evaluated_events(&self) -> usize {
+		if self.rolling_log.is_some() {
+			return self.window().evaluated_events();
+		}
+		if self.settings.evaluate_cursor_when_window_empty && self.buffer.evaluated_events() == 0 {
+			return self.buffer.evaluated_events_trailing(usize::MAX, true);
+		}
+
+		self.buffer.evaluated_events_trailing(self.settings.denominator_policy.spans(), self.settings.include_current_span)
+	}
+
+	/// Get the error rate formatted as a percentage with two decimals, e.g.
+	/// `"12.50%"`
+	pub fn error_rate_display(&self) -> String {
+		format!("{:.2}%", self.get_error_rate())
+	}
+
+	/// Evaluate the state and get it together with the inputs that determined
+	/// it, e.g. to debug why a circuit is open or closed
+	pub fn explain_state(&mut self) -> StateExplanation {
+		StateExplanation {
+			state: self.get_state(),
+			error_rate: self.get_error_rate(),
+			evaluated_events: self.evaluated_events(),
+			min_eval_size: self.settings.min_eval_size,
+			trial_success: self.trial_success,
+			trial_success_required: self.settings.trial_success_required,
+			time_until_transition: self.time_until_retry(),
+			open_reason: self.open_reason,
+		}
+	}
+
+	/// Get how full the [Window] is relative to `min_eval_size`, from 0.0 to
+	/// 1.0. Below 1.0 there isn't enough data yet to evaluate the error rate.
+	pub fn buffer_utilization(&self) -> f32 {
+		if self.settings.min_eval_size == 0 {
+			return 1.0;
+		}
+
+		(self.evaluated_events() as f32 / self.settings.min_eval_size as f32).min(1.0)
+	}
+
+	/// Estimate how many events the whole [Window] holds at `assumed_rps`
+	/// requests per second before it rolls over, e.g. to tune `min_eval_size`.
+	/// This is advisory only as the counters of each span are unbounded.
+	///
+	/// Event-based windows hold a fixed number of events no matter the rate:
+	/// `buffer_size` spans of `span_events` or the rolling log capacity.
+	pub fn capacity_events(&self, assumed_rps: u32) -> usize {
+		match self.settings.window_mode {
+			WindowMode::Spans => {
+				let window_millis =
+					self.settings.buffer_span_duration.as_millis().saturating_mul(self.settings.buffer_size as u128);
+				let events = window_millis.saturating_mul(u128::from(assumed_rps)).checked_div(1000).unwrap_or(0);
+				usize::try_from(events).unwrap_or(usize::MAX)
+			},
+			WindowMode::SpanEvents(span_events) => span_events.max(1).saturating_mul(self.settings.buffer_size),
+			WindowMode::RollingLog(capacity) => capacity,
+		}
+	}
+
+	/// Check if the [Settings] allow the circuit to ever open.
+	///
+	/// A rolling log holding fewer events than `min_eval_size` is never evaluated
+	/// and an error rate can't exceed 100%, so it only reaches a threshold of
+	/// exactly 100% with `Settings.inclusive_threshold`.
+	pub fn can_ever_trip(&self) -> bool {
+		let enough_events = match self.settings.window_mode {
+			WindowMode::Spans | WindowMode::SpanEvents(_) => true,
+			WindowMode::RollingLog(capacity) => capacity >= self.settings.min_eval_size,
+		};
+
+		enough_events && self.settings.trips_at(100.0)
+	}
+
+	/// Get how long ago the last event was recorded, e.g. to tell an idle
+	/// breaker from a stuck one
+	pub fn since_last_record(&self, now: Instant) -> Duration {
+		now.saturating_duration_since(self.last_event)
+	}
+
+	/// Get a stable, machine-readable JSON summary of the [CircuitBreaker]
+	/// without evaluating the state first:
+	///
+	/// - `state`: one of `"closed"`, `"open"` or `"half_open"`
+	/// - `error_rate`: the current error rate percentage
+	/// - `cursor`: the index of the current node in the ring buffer
+	/// - `buffer_size`: the number of nodes in the ring buffer
+	/// - `trial_success`: the successes recorded in HalfOpen state
+	pub fn summary_json(&self) -> String {
+		let state = match self.state {
+			State::Closed => "closed",
+			State::Open(_) => "open",
+			State::HalfOpen => "half_open",
+		};
+
+		// unnamed breakers keep the output without a name
+		let name = if self.name.is_empty() {
+			String::new()
+		} else {
+			format!(r#""name":"{}","#, escape_json(&self.name))
+		};
+
+		format!(
+			r#"{{{name}"state":"{state}","error_rate":{},"cursor":{},"buffer_size":{},"trial_success":{}}}"#,
+			self.get_error_rate(),
+			self.buffer.get_cursor(),
+			self.buffer.get_size(),
+			self.trial_success
+		)
+	}
+
+	/// Get the error rate of each span of the ring buffer on its own, ordered
+	/// from the oldest to the current span, e.g. to draw a sparkline of the
+	/// trend. Empty spans and the still filling current span read 0.0.
+	pub fn span_error_rates(&self) -> Vec<f32> {
+		let mut rates: Vec<f32> = self.buffer.oldest_to_newest().iter().map(NodeInfo::error_rate).collect();
+		if let Some(current) = rates.last_mut() {
+			*current = 0.0;
+		}
+		rates
+	}
+
+	/// Get every span of the ring buffer, ordered by index, with the time range
+	/// it covers as of `now`. The ranges start at `last_record` plus the whole
+	/// spans elapsed since, so spans the buffer would advance past on the next
+	/// record are reported empty and the span `now` falls into is the active
+	/// one, even if nothing was recorded for a while.
+	pub fn spans(&self, now: Instant) -> Vec<SpanView> {
+		let span = self.settings.buffer_span_duration;
+		let size = self.buffer.get_size();
+		// the spans the next record advances by, see `advance_buffer_for_time`
+		let steps = match self.settings.window_mode {
+			WindowMode::SpanEvents(_) => 0,
+			_ => now.saturating_duration_since(self.last_record).as_nanos().checked_div(span.as_nanos()).unwrap_or(0),
+		};
+		let active_start = u32::try_from(steps)
+			.ok()
+			.and_then(|steps| span.checked_mul(steps))
+			.and_then(|offset| self.last_record.checked_add(offset))
+			.unwrap_or(now);
+		let steps = usize::try_from(steps).unwrap_or(usize::MAX);
+		let active =
+			self.buffer.get_cursor().saturating_add(steps.checked_rem(size).unwrap_or(0)).checked_rem(size).unwrap_or(0);
+
+		self.buffer.fold_nodes(Vec::with_capacity(size), |mut spans, index, info| {
+			// how many spans before the active span this one started
+			let age = active.saturating_add(size).saturating_sub(index).checked_rem(size).unwrap_or(0);
+			let start = u32::try_from(age)
+				.ok()
+				.and_then(|age| span.checked_mul(age))
+				.and_then(|offset| active_start.checked_sub(offset))
+				.unwrap_or(active_start);
+			spans.push(SpanView {
+				index,
+				start,
+				end: start.checked_add(span).unwrap_or(start),
+				info: if age < steps {
+					NodeInfo {
+						failure_count: 0,
+						success_count: 0,
+					}
+				} else {
+					info
+				},
+				active: index == active,
+			});
+			spans
+		})
+	}
+
+	/// Get the elapsed time of our current phase
+	pub fn get_elapsed_time(&self, buffer_span_duration: Duration, now: Instant) -> Duration {
+		let elapsed = now.duration_since(self.start_time);
+		let remainder_ns = elapsed.as_nanos().checked_rem(buffer_span_duration.as_nanos()).unwrap_or(u128::MAX);
+		Duration::from_nanos(remainder_ns as u64)
+	}
+
+	/// Get the timing math `advance_buffer_for_time` would use at `now`, e.g. to
+	/// diagnose an unexpected cursor position
+	pub fn debug_timing(&self, now: Instant) -> TimingInfo {
+		let last_record_age = now.saturating_duration_since(self.last_record);
+		let span_nanos = self.settings.buffer_span_duration.as_nanos();
+		let remainder = last_record_age.as_nanos().checked_rem(span_nanos).unwrap_or(0);
+		TimingInfo {
+			last_record_age,
+			span_elapsed: Duration::from_nanos(u64::try_from(remainder).unwrap_or(u64::MAX)),
+			spans_since_last_record: last_record_age.as_nanos().checked_div(span_nanos).unwrap_or(0),
+		}
+	}
+
+	/// Get the time left at `now` until the current span ends and the ring
+	/// buffer advances to the next one. Only meaningful for time-based spans.
+	pub fn time_budget_remaining(&self, now: Instant) -> Duration {
+		let buffer_span_duration = self.settings.buffer_span_duration;
+		buffer_span_duration.saturating_sub(self.get_elapsed_time(buffer_span_duration, now))
+	}
+}
+
+/// Escape `input` to be used inside a JSON string
+fn escape_json(input: &str) -> String {
+	let mut escaped = String::with_capacity(input.len());
+	for c in input.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+impl Default for CircuitBreaker {
+	fn default() -> Self {
+		Self::new(Settings::default())
+	}
+}
+
+impl std::fmt::Debug for CircuitBreaker {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if f.alternate() {
+			f.debug_struct("CircuitBreaker")
+				.field("buffer", &self.buffer)
+				.field("state", &self.state)
+				.field("last_record", &self.last_record)
+				.field("closed_at", &self.closed_at)
+				.field("last_event", &self.last_event)
+				.field("start_time", &self.start_time)
+				.field("span_event_count", &self.span_event_count)
+				.field("trial_success", &self.trial_success)
+				.field("trial_failure", &self.trial_failure)
+				.field("consecutive_failures", &self.consecutive_failures)
+				.field("open_reason", &self.open_reason)
+				.field("sticky_error_rate", &self.sticky_error_rate)
+				.field("lifetime_successes", &self.lifetime_successes)
+				.field("lifetime_failures", &self.lifetime_failures)
+				.field("state_since", &self.state_since)
+				.field("state_durations", &self.state_durations)
+				.field("state_entry_counts", &self.state_entry_counts)
+				.field("rolling_log", &self.rolling_log)
+				.field("probe_buffer", &self.probe_buffer)
+				.field("atomic_buffer", &self.atomic_buffer)
+				.field("labels", &self.labels)
+				.field("settings", &self.settings)
+				.field("transitions", &self.transitions)
+				.field("hooks", &self.hooks)
+				.field("clock", &self.clock)
+				.field("metrics", &self.metrics)
+				.field("paused_at", &self.paused_at)
+				.field("last_evaluated_at", &self.last_evaluated_at)
+				.field("trial_decayed_at", &self.trial_decayed_at)
+				.field("name", &self.name)
+				.finish()
+		} else {
+			let mut debug = f.debug_struct("CircuitBreaker");
+			if !self.name.is_empty() {
+				debug.field("name", &self.name);
+			}
+			debug
+				.field("state", &self.state)
+				.field("error_rate", &self.get_error_rate())
+				.field("cursor", &self.buffer.get_cursor())
+				.field("buffer_size", &self.buffer.get_size())
+				.field("trial_success", &self.trial_success)
+				.field("trial_success_required", &self.settings.trial_success_required)
+				.finish()
+		}
+	}
+}
+
+/// Record each outcome, `true` being a success, as if passed to `record`
+impl Extend<bool> for CircuitBreaker {
+	fn extend<I: IntoIterator<Item = bool>>(&mut self, outcomes: I) {
+		for success in outcomes {
+			self.record_bool(success);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex,
+	};
+
+	use super::*;
+	use crate::clock::ManualClock;
+
+	#[test]
+	fn state_label_test() {
+		assert_eq!(State::Closed.label(), "Closed");
+		assert_eq!(State::Open(Instant::now()).label(), "Open");
+		assert_eq!(State::HalfOpen.label(), "Half Open");
+	}
+
+	#[test]
+	fn state_ansi_color_test() {
+		assert_eq!(State::Closed.ansi_color(), "");
+		assert_eq!(State::Open(Instant::now()).ansi_color(), "\x1b[41m");
+		assert_eq!(State::HalfOpen.ansi_color(), "\x1b[43m");
+	}
+
+	#[test]
+	fn state_fmt_test() {
+		assert_eq!(format!("{}", State::Open(Instant::now())), String::from("\x1b[41m Open \x1b[0m     "));
+		assert_eq!(format!("{:#}", State::Open(Instant::now())), String::from("\x1b[0m─"));
+		assert_eq!(format!("{}", State::Closed), String::from("Closed     "));
+		assert_eq!(format!("{:#}", State::Closed), String::from("│"));
+		assert_eq!(format!("{}", State::HalfOpen), String::from("\x1b[43m Half Open \x1b[0m"));
+		assert_eq!(format!("{:#}", State::HalfOpen), String::from("/"));
+	}
+
+	#[test]
+	fn new_test() {
+		assert_eq!(CircuitBreaker::new(Settings::default()).buffer.get_size(), 5);
+		assert_eq!(CircuitBreaker::new(Settings::default()).settings, Settings::default());
+		assert_eq!(
+			CircuitBreaker::new(Settings {
+				buffer_size: 10,
+				..Settings::default()
+			})
+			.buffer
+			.get_size(),
+			10
+		);
+		assert_eq!(
+			CircuitBreaker::new(Settings {
+				buffer_size: 666,
+				min_eval_size: 5,
+				error_threshold: 99.99,
+				threshold_mode: ThresholdMode::Absolute,
+				retry_timeout: Duration::from_millis(20),
+				buffer_span_duration: Duration::from_millis(999),
+				trial_success_required: 42,
+				window_mode: WindowMode::Spans,
+				transition_log_size: 10,
+				shadow: false,
+				close_cooldown: Duration::ZERO,
+				recover_on_error_rate: None,
+				include_current_span: false,
+				trip_on_consecutive: None,
+				inclusive_threshold: false,
+				trip_priority: TripKind::DEFAULT_PRIORITY,
+				sticky_after_idle: false,
+				open_policy: OpenPolicy::FailClosed,
+				half_open_buffer_size: None,
+				denominator_policy: DenominatorPolicy::WholeWindow,
+				p95_slow_threshold: None,
+				eval_debounce: None,
+				post_recovery_sensitive: Duration::ZERO,
+				trial_decay_interval: None,
+				max_buffer_size: 1_000_000,
+				evaluate_cursor_when_window_empty: false,
+			})
+			.settings,
+			Settings {
+				buffer_size: 666,
+				min_eval_size: 5,
+				error_threshold: 99.99,
+				threshold_mode: ThresholdMode::Absolute,
+				retry_timeout: Duration::from_millis(20),
+				buffer_span_duration: Duration::from_millis(999),
+				trial_success_required: 42,
+				window_mode: WindowMode::Spans,
+				transition_log_size: 10,
+				shadow: false,
+				close_cooldown: Duration::ZERO,
+				recover_on_error_rate: None,
+				include_current_span: false,
+				trip_on_consecutive: None,
+				inclusive_threshold: false,
+				trip_priority: TripKind::DEFAULT_PRIORITY,
+				sticky_after_idle: false,
+				open_policy: OpenPolicy::FailClosed,
+				half_open_buffer_size: None,
+				denominator_policy: DenominatorPolicy::WholeWindow,
+				p95_slow_threshold: None,
+				eval_debounce: None,
+				post_recovery_sensitive: Duration::ZERO,
+				trial_decay_interval: None,
+				max_buffer_size: 1_000_000,
+				evaluate_cursor_when_window_empty: false,
+			}
+		);
+	}
+
+	#[test]
+	fn try_new_test() {
+		assert!(CircuitBreaker::try_new(Settings::default()).is_ok());
+		assert_eq!(
+			CircuitBreaker::try_new(Settings {
+				buffer_size: 0,
+				..Settings::default()
+			}),
+			Err(SettingsError::ZeroBufferSize)
+		);
+		assert_eq!(
+			CircuitBreaker::try_new(Settings {
+				window_mode: WindowMode::RollingLog(0),
+				..Settings::default()
+			}),
+			Err(SettingsError::ZeroRollingLogCapacity)
+		);
+		assert_eq!(
+			CircuitBreaker::try_new(Settings {
+				half_open_buffer_size: Some(0),
+				..Settings::default()
+			}),
+			Err(SettingsError::ZeroHalfOpenBufferSize)
+		);
+		assert_eq!(
+			CircuitBreaker::try_new(Settings {
+				denominator_policy: DenominatorPolicy::LastN(0),
+				..Settings::default()
+			}),
+			Err(SettingsError::ZeroLastNSpans)
+		);
+		assert_eq!(
+			CircuitBreaker::try_new(Settings {
+				error_threshold: -1.0,
+				..Settings::default()
+			}),
+			Err(SettingsError::InvalidErrorThreshold(-1.0))
+		);
+		assert!(Settings {
+			error_threshold: f32::NAN,
+			..Settings::default()
+		}
+		.validate()
+		.is_err());
+		assert_eq!(
+			CircuitBreaker::try_new(Settings {
+				buffer_size: 100_000_000,
+				..Settings::default()
+			}),
+			Err(SettingsError::BufferSizeTooLarge {
+				size: 100_000_000,
+				max: 1_000_000,
+			})
+		);
+		assert!(CircuitBreaker::try_new(Settings {
+			buffer_size: 2_000_000,
+			max_buffer_size: 2_000_000,
+			..Settings::default()
+		})
+		.is_ok());
+		assert_eq!(
+			CircuitBreaker::try_new(Settings {
+				half_open_buffer_size: Some(8),
+				max_buffer_size: 5,
+				..Settings::default()
+			}),
+			Err(SettingsError::HalfOpenBufferSizeTooLarge { size: 8, max: 5 })
+		);
+		assert_eq!(
+			CircuitBreaker::try_new(Settings {
+				window_mode: WindowMode::RollingLog(2_000_000),
+				..Settings::default()
+			}),
+			Err(SettingsError::RollingLogCapacityTooLarge {
+				capacity: 2_000_000,
+				max: 1_000_000,
+			})
+		);
+	}
+
+	#[test]
+	fn get_state_test() {
+		assert_eq!(CircuitBreaker::new(Settings::default()).get_state(), State::Closed);
+	}
+
+	#[test]
+	fn advance_buffer_for_time_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let last_record = Instant::now();
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(3),
+			state: State::Closed,
+			last_record,
+			start_time: Instant::now(),
+			trial_success: 0,
+			settings: Settings {
+				buffer_span_duration,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(1),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(2),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(cb.get_buffer().get_cursor(), 0);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 2,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(1),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(2),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+
+		cb.advance_buffer_for_time(last_record);
+		assert_eq!(cb.get_buffer().get_cursor(), 0);
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 2,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(1),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(2),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+
+		cb.advance_buffer_for_time(last_record + buffer_span_duration);
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 2,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(1),
+			NodeInfo {
+				failure_count: 2,
+				success_count: 2,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(2),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+
+		cb.advance_buffer_for_time(last_record + buffer_span_duration + buffer_span_duration);
+		assert_eq!(cb.get_buffer().get_cursor(), 2);
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 2,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(1),
+			NodeInfo {
+				failure_count: 2,
+				success_count: 2,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(2),
+			NodeInfo {
+				failure_count: 1,
+				success_count: 1,
+			}
+		);
+
+		cb.advance_buffer_for_time(
+			last_record
+				+ buffer_span_duration
+				+ buffer_span_duration
+				+ buffer_span_duration
+				+ buffer_span_duration
+				+ buffer_span_duration,
+		);
+		assert_eq!(cb.get_buffer().get_cursor(), 2);
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(1),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(2),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Err(""));
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(1),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+		assert_eq!(
+			cb.get_buffer().get_node_info(2),
+			NodeInfo {
+				failure_count: 2,
+				success_count: 1,
+			}
+		);
+	}
+
+	#[test]
+	fn advance_buffer_for_time_no_drift_test() {
+		let clock = ManualClock::default();
+		let start = clock.now();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				buffer_size: 10,
+				buffer_span_duration: Duration::from_secs(1),
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+
+		// 70 events every 300ms span 21s, the sub-span remainders add up
+		for _ in 0..70 {
+			clock.advance(Duration::from_millis(300));
+			cb.record::<(), ()>(Ok(()));
+		}
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+		assert_eq!(cb.last_record, start + Duration::from_secs(21));
+		// the span from 20s holds the events at 20.1s, 20.4s and 20.7s
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 3);
+		assert_eq!(cb.get_buffer().get_node_info(1).success_count, 1);
+	}
+
+	#[test]
+	fn advance_buffer_for_time_idle_test() {
+		let last_record = Instant::now();
+		let mut cb = CircuitBreaker {
+			last_record,
+			settings: Settings {
+				buffer_span_duration: Duration::from_nanos(1),
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		cb.get_buffer().add_failures(5);
+		cb.advance_buffer_for_time(last_record + Duration::from_nanos(2));
+		cb.get_buffer().add_failures(5);
+		assert_eq!(cb.get_buffer().get_cursor(), 2);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 5);
+
+		// more nanoseconds than fit into a usize
+		cb.advance_buffer_for_time(last_record + Duration::from_secs(100_000_000_000));
+		for i in 0..cb.get_buffer().get_size() {
+			assert_eq!(cb.get_buffer().get_node_info(i).failure_count, 0);
+		}
+	}
+
+	#[test]
+	fn call_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		assert!(cb.is_call_permitted());
+		assert_eq!(cb.call(|| Ok::<usize, &str>(42)), Ok(42));
+		assert_eq!(cb.call(|| Err::<(), &str>("nope")), Err(CircuitError::Inner("nope")));
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				success_count: 1,
+				failure_count: 1,
+			}
+		);
+
+		cb.state = State::Open(Instant::now());
+		assert!(!cb.is_call_permitted());
+		assert_eq!(cb.call(|| Ok::<usize, &str>(42)), Err(CircuitError::Open));
+	}
+
+	#[test]
+	fn shadow_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			buffer_span_duration,
+			shadow: true,
+			..Settings::default()
+		});
+		assert_eq!(cb.call(|| Err::<(), &str>("nope")), Err(CircuitError::Inner("nope")));
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+
+		// the state machine still opens the circuit but calls are permitted
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert!(cb.is_call_permitted());
+		assert_eq!(cb.call(|| Ok::<usize, &str>(42)), Ok(42));
+		assert!(cb.guard().is_some());
+	}
+
+	#[test]
+	fn half_open_buffer_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			error_threshold: 30.0,
+			trial_success_required: 4,
+			half_open_buffer_size: Some(4),
+			..Settings::default()
+		});
+		cb.transition_to(State::HalfOpen, Instant::now());
+
+		// one failed probe doesn't reopen the circuit right away
+		cb.record::<(), ()>(Err(()));
+		cb.record::<(), ()>(Ok(()));
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		// 25% of the last 4 probes failed which is below the threshold
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.probe_buffer, None);
+
+		cb.transition_to(State::HalfOpen, Instant::now());
+		cb.record_many_successes(2);
+		cb.record_many_failures(2);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.get_open_reason(), Some(OpenReason::TrialFailed));
+	}
+
+	#[test]
+	fn fail_open_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			buffer_span_duration,
+			open_policy: OpenPolicy::FailOpen,
+			..Settings::default()
+		});
+		cb.record::<(), ()>(Err(()));
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+
+		// calls are permitted while open but not recorded
+		assert!(cb.is_call_permitted());
+		assert_eq!(cb.call(|| Err::<(), &str>("nope")), Err(CircuitError::Inner("nope")));
+		assert_eq!(cb.call(|| Ok::<usize, &str>(42)), Ok(42));
+		assert_eq!(cb.lifetime_totals(), (0, 1));
+		assert_eq!(cb.drain_metrics().rejected_count, 0);
+	}
+
+	#[test]
+	fn record_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		assert_eq!(
+			cb.buffer.get_node_info(0),
+			NodeInfo {
+				success_count: 0,
+				failure_count: 0,
+			}
+		);
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(
+			cb.buffer.get_node_info(0),
+			NodeInfo {
+				success_count: 1,
+				failure_count: 0,
+			}
+		);
+		cb.record::<(), &str>(Err(""));
+		assert_eq!(
+			cb.buffer.get_node_info(0),
+			NodeInfo {
+				success_count: 1,
+				failure_count: 1,
+			}
+		);
+
+		cb.state = State::Open(Instant::now());
+		assert_eq!(
+			cb.buffer.get_node_info(0),
+			NodeInfo {
+				success_count: 1,
+				failure_count: 1,
+			}
+		);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Err(""));
+		assert_eq!(
+			cb.buffer.get_node_info(0),
+			NodeInfo {
+				success_count: 1,
+				failure_count: 1,
+			}
+		);
+
+		cb.state = State::HalfOpen;
+		assert_eq!(
+			cb.buffer.get_node_info(0),
+			NodeInfo {
+				success_count: 1,
+				failure_count: 1,
+			}
+		);
+		assert_eq!(cb.trial_success, 0);
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(
+			cb.buffer.get_node_info(0),
+			NodeInfo {
+				success_count: 1,
+				failure_count: 1,
+			}
+		);
+		assert_eq!(cb.trial_success, 1);
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(
+			cb.buffer.get_node_info(0),
+			NodeInfo {
+				success_count: 1,
+				failure_count: 1,
+			}
+		);
+		assert_eq!(cb.trial_success, 2);
+		cb.record::<(), &str>(Err(""));
+		assert!(matches!(cb.state, State::Open(_)));
+	}
+
+	#[test]
+	fn record_timed_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			buffer_span_duration,
+			..Settings::default()
+		});
+
+		cb.advance_buffer_for_time(Instant::now());
+		assert_eq!(cb.get_buffer().get_cursor(), 0);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		std::thread::sleep(buffer_span_duration);
+		cb.advance_buffer_for_time(Instant::now());
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+		assert_eq!(cb.buffer.get_node_info(0).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(1).success_count, 0);
+		assert_eq!(cb.buffer.get_node_info(2).success_count, 0);
+		assert_eq!(cb.buffer.get_node_info(3).success_count, 0);
+		assert_eq!(cb.buffer.get_node_info(4).success_count, 0);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		std::thread::sleep(buffer_span_duration);
+		cb.advance_buffer_for_time(Instant::now());
+		assert_eq!(cb.get_buffer().get_cursor(), 2);
+		assert_eq!(cb.buffer.get_node_info(0).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(1).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(2).success_count, 0);
+		assert_eq!(cb.buffer.get_node_info(3).success_count, 0);
+		assert_eq!(cb.buffer.get_node_info(4).success_count, 0);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		std::thread::sleep(buffer_span_duration);
+		cb.advance_buffer_for_time(Instant::now());
+		assert_eq!(cb.get_buffer().get_cursor(), 3);
+		assert_eq!(cb.buffer.get_node_info(0).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(1).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(2).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(3).success_count, 0);
+		assert_eq!(cb.buffer.get_node_info(4).success_count, 0);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		std::thread::sleep(buffer_span_duration);
+		cb.advance_buffer_for_time(Instant::now());
+		assert_eq!(cb.get_buffer().get_cursor(), 4);
+		assert_eq!(cb.buffer.get_node_info(0).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(1).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(2).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(3).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(4).success_count, 0);
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		std::thread::sleep(buffer_span_duration);
+		cb.advance_buffer_for_time(Instant::now());
+		assert_eq!(cb.get_buffer().get_cursor(), 0);
+		assert_eq!(cb.buffer.get_node_info(0).success_count, 0);
+		assert_eq!(cb.buffer.get_node_info(1).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(2).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(3).success_count, 3);
+		assert_eq!(cb.buffer.get_node_info(4).success_count, 3);
+		cb.record::<(), &str>(Ok(()));
+
+		// We skip 3 nodes ahead
+		std::thread::sleep(buffer_span_duration + buffer_span_duration + buffer_span_duration);
+		cb.evaluate_state();
+
+		assert_eq!(cb.buffer.get_node_info(0).success_count, 1);
+		assert_eq!(cb.buffer.get_node_info(1).success_count, 0); // skipped
+		assert_eq!(cb.buffer.get_node_info(2).success_count, 0); // skipped
+		assert_eq!(cb.buffer.get_node_info(3).success_count, 0); // current
+		assert_eq!(cb.buffer.get_node_info(4).success_count, 3);
+		cb.advance_buffer_for_time(Instant::now());
+		assert_eq!(cb.get_buffer().get_cursor(), 3);
+	}
+
+	#[test]
+	fn record_labeled_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		cb.record_labeled::<(), &str>("read", Ok(()));
+		cb.record_labeled::<(), &str>("read", Ok(()));
+		cb.record_labeled::<(), &str>("read", Ok(()));
+		cb.record_labeled::<(), &str>("read", Err(""));
+		cb.record_labeled::<(), &str>("write", Err(""));
+		cb.record_labeled::<(), &str>("write", Ok(()));
+
+		assert_eq!(cb.label_error_rate("read"), Some(25.0));
+		assert_eq!(cb.label_error_rate("write"), Some(50.0));
+		assert_eq!(cb.label_error_rate("delete"), None);
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				success_count: 4,
+				failure_count: 2,
+			}
+		);
+	}
+
+	#[test]
+	fn record_many_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		cb.record_many_successes(1000);
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				success_count: 1000,
+				failure_count: 0,
+			}
+		);
+		assert_eq!(cb.get_error_rate(), 0.0);
+		cb.record_many_failures(10);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 10);
+
+		cb.state = State::HalfOpen;
+		cb.record_many_successes(5);
+		assert_eq!(cb.trial_success, 5);
+		cb.record_many_failures(0);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.record_many_failures(1);
+		assert!(matches!(cb.state, State::Open(_)));
+		cb.record_many_successes(100);
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 1000);
+
+		// recording nothing doesn't count as recording
+		let recorded = Arc::new(Mutex::new(Vec::new()));
+		let mut cb = CircuitBreaker::default();
+		let sink = recorded.clone();
+		cb.on_record(move |successes, failures| sink.lock().unwrap().push((successes, failures)));
+		cb.record_many_successes(0);
+		cb.record_many_failures(0);
+		assert!(recorded.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	fn evaluate_cursor_when_window_empty_test() {
+		let settings = Settings {
+			min_eval_size: 5,
+			..Settings::default()
+		};
+
+		let mut cb = CircuitBreaker::new(settings);
+		cb.record_many_failures(5);
+		assert_eq!(cb.get_error_rate(), 0.0);
+		assert_eq!(cb.get_state(), State::Closed);
+
+		let mut cb = CircuitBreaker::new(Settings {
+			evaluate_cursor_when_window_empty: true,
+			..settings
+		});
+		cb.record_many_failures(4);
+		assert_eq!(cb.get_state(), State::Closed);
+		cb.record_many_failures(1);
+		assert_eq!(cb.get_error_rate(), 100.0);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+	}
+
+	#[test]
+	fn error_rate_display_test() {
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(5),
+			settings: Settings {
+				min_eval_size: 1,
+				include_current_span: true,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		cb.buffer.add_success();
+		assert_eq!(cb.error_rate_display(), "0.00%");
+
+		for _ in 0..6 {
+			cb.buffer.add_success();
+		}
+		cb.buffer.add_failure();
+		assert_eq!(cb.error_rate_display(), "12.50%");
+
+		cb.buffer = RingBuffer::new(5);
+		cb.buffer.add_failure();
+		assert_eq!(cb.error_rate_display(), "100.00%");
+	}
+
+	#[test]
+	fn evaluate_now_test() {
+		let retry_timeout = Duration::from_secs(30);
+		let opened_at = Instant::now();
+		let mut cb = CircuitBreaker {
+			state: State::Open(opened_at),
+			settings: Settings {
+				retry_timeout,
+				transition_log_size: 1,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+
+		cb.evaluate_now(opened_at + retry_timeout - Duration::from_millis(1));
+		assert_eq!(cb.peek_state(), State::Open(opened_at));
+
+		let now = opened_at + retry_timeout;
+		cb.evaluate_now(now);
+		assert_eq!(cb.peek_state(), State::HalfOpen);
+		assert_eq!(cb.transition_history().last().map(|transition| transition.at), Some(now));
+	}
+
+	#[test]
+	fn evaluate_state_test() {
+		// Open state within the retry_timeout time
+		let retry_timeout = Duration::from_secs(1);
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(5),
+			state: State::Open(Instant::now()),
+			last_record: Instant::now(),
+			start_time: Instant::now(),
+			trial_success: 0,
+			settings: Settings {
+				retry_timeout,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		cb.evaluate_state();
+		assert!(matches!(cb.get_state(), State::Open(_)));
+
+		// Open state after the retry_timeout time
+		let retry_timeout = Duration::from_secs(1);
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(5),
+			state: State::Open(Instant::now() - retry_timeout),
+			last_record: Instant::now(),
+			start_time: Instant::now(),
+			trial_success: 0,
+			settings: Settings {
+				retry_timeout,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		cb.evaluate_state();
+		assert_eq!(cb.get_state(), State::HalfOpen);
+
+		// Closed state within the margin of error
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(5),
+			state: State::Closed,
+			last_record: Instant::now(),
+			start_time: Instant::now(),
+			trial_success: 0,
+			settings: Settings {
+				min_eval_size: 4,
+				error_threshold: 39.99999,
+				buffer_span_duration,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		assert_eq!(cb.get_error_rate(), 20.0);
+		cb.evaluate_state();
+		assert_eq!(cb.get_state(), State::Closed);
+
+		// Closed state an error larger than error_threshold
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(5),
+			state: State::Closed,
+			last_record: Instant::now(),
+			start_time: Instant::now(),
+			trial_success: 0,
+			settings: Settings {
+				min_eval_size: 4,
+				error_threshold: 39.99999,
+				buffer_span_duration,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		assert_eq!(cb.get_error_rate(), 40.0);
+		cb.evaluate_state();
+		assert!(matches!(cb.get_state(), State::Open(_)));
+
+		// HalfOpen state with slowly increasing trial_success
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(5),
+			state: State::HalfOpen,
+			last_record: Instant::now(),
+			start_time: Instant::now(),
+			trial_success: 0,
+			settings: Settings {
+				trial_success_required: 5,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		cb.evaluate_state();
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.trial_success = 4;
+		cb.evaluate_state();
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.trial_success += 1;
+		cb.evaluate_state();
+		assert_eq!(cb.get_state(), State::Closed);
+	}
+
+	#[test]
+	fn relative_threshold_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(5),
+			state: State::Closed,
+			last_record: Instant::now(),
+			start_time: Instant::now(),
+			trial_success: 0,
+			settings: Settings {
+				min_eval_size: 100,
+				threshold_mode: ThresholdMode::RelativeToBaseline {
+					baseline: 1.0,
+					margin: 1.5,
+				},
+				buffer_span_duration,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		assert_eq!(cb.get_settings().trip_threshold(), 2.5);
+
+		cb.record_many_failures(3);
+		cb.record_many_successes(97);
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		assert_eq!(cb.get_error_rate(), 3.0);
+		cb.evaluate_state();
+		assert!(matches!(cb.get_state(), State::Open(_)));
+	}
+
+	#[test]
+	fn preset_test() {
+		for name in Settings::PRESETS {
+			assert!(Settings::preset(name).is_some(), "Preset '{name}' is missing");
+		}
+		assert_eq!(Settings::preset("default"), Some(Settings::default()));
+		assert_eq!(Settings::preset("aggressive").unwrap().error_threshold, 5.0);
+		assert_eq!(Settings::preset("unknown"), None);
+	}
+
+	#[test]
+	fn requires_rebuild_test() {
+		let settings = Settings::default();
+		assert!(!settings.requires_rebuild(&settings));
+		assert!(!settings.requires_rebuild(&Settings {
+			error_threshold: 42.0,
+			..settings
+		}));
+		assert!(!settings.requires_rebuild(&Settings {
+			min_eval_size: 1,
+			retry_timeout: Duration::from_secs(1),
+			..settings
+		}));
+		assert!(settings.requires_rebuild(&Settings {
+			buffer_size: 10,
+			..settings
+		}));
+		assert!(settings.requires_rebuild(&Settings {
+			buffer_span_duration: Duration::from_secs(1),
+			..settings
+		}));
+		assert!(settings.requires_rebuild(&Settings {
+			window_mode: WindowMode::RollingLog(10),
+			..settings
+		}));
+		assert!(settings.requires_rebuild(&Settings {
+			window_mode: WindowMode::SpanEvents(10),
+			..settings
+		}));
+	}
+
+	#[test]
+	fn inclusive_threshold_test() {
+		for inclusive_threshold in [false, true] {
+			let buffer_span_duration = Duration::from_secs(1);
+			let mut cb = CircuitBreaker::new(Settings {
+				min_eval_size: 1,
+				error_threshold: 40.0,
+				buffer_span_duration,
+				inclusive_threshold,
+				..Settings::default()
+			});
+			cb.get_buffer().add_failures(2);
+			cb.get_buffer().add_successes(3);
+			cb.get_buffer().advance(1);
+			assert_eq!(cb.get_error_rate(), 40.0);
+			assert_eq!(matches!(cb.get_state(), State::Open(_)), inclusive_threshold);
+			assert_eq!(cb.get_settings().trips_at(40.0), inclusive_threshold);
+			assert!(cb.get_settings().trips_at(40.01));
+		}
+	}
+
+	#[test]
+	fn trip_threshold_test() {
+		assert_eq!(Settings::default().trip_threshold(), 10.0);
+		assert_eq!(
+			Settings {
+				error_threshold: 50.0,
+				threshold_mode: ThresholdMode::RelativeToBaseline {
+					baseline: 2.0,
+					margin: 3.5,
+				},
+				..Settings::default()
+			}
+			.trip_threshold(),
+			5.5
+		);
+	}
+
+	#[test]
+	fn replace_settings_preserving_state_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 4,
+			error_threshold: 50.0,
+			buffer_span_duration,
+			..Settings::default()
+		});
+		cb.record_many_failures(1);
+		cb.record_many_successes(3);
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		assert_eq!(cb.get_error_rate(), 25.0);
+		assert_eq!(cb.get_state(), State::Closed);
+
+		cb.replace_settings_preserving_state(Settings {
+			error_threshold: 20.0,
+			..*cb.get_settings()
+		});
+		assert_eq!(cb.get_settings().error_threshold, 20.0);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 3);
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+		assert_eq!(cb.get_error_rate(), 25.0);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+
+		cb.replace_settings_preserving_state(Settings {
+			buffer_size: 2,
+			..*cb.get_settings()
+		});
+		assert!(matches!(cb.state, State::Open(_)));
+		assert_eq!(cb.get_buffer().get_size(), 2);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+	}
+
+	#[test]
+	fn clone_test() {
+		let opened = Arc::new(AtomicUsize::new(0));
+		let buffer_span_duration = Duration::from_secs(1);
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				buffer_span_duration,
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		let counter = opened.clone();
+		cb.on_open_emit_once(move || {
+			counter.fetch_add(1, Ordering::SeqCst);
+		});
+		cb.record::<(), ()>(Ok(()));
+
+		let mut clone = cb.clone();
+		assert_eq!(clone, cb);
+		clone.record::<(), ()>(Err(()));
+		assert_eq!(clone.get_buffer().get_node_info(0).failure_count, 1);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 0);
+
+		// the clone shares the clock but not the hooks
+		clock.advance(buffer_span_duration);
+		assert!(matches!(clone.get_state(), State::Open(_)));
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(opened.load(Ordering::SeqCst), 0);
+	}
+
+	#[test]
+	fn try_close_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				include_current_span: true,
+				trial_success_required: 10,
+				recover_on_error_rate: Some(50.0),
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		assert_eq!(cb.try_close(), Ok(()));
+
+		cb.record_many_failures(1);
+		let opened = cb.get_state();
+		assert!(matches!(opened, State::Open(_)));
+		assert_eq!(cb.try_close(), Err(opened));
+
+		clock.advance(cb.get_settings().retry_timeout);
+		assert_eq!(cb.try_close(), Err(State::HalfOpen));
+		cb.record::<(), ()>(Err(()));
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		assert_eq!(cb.try_close(), Err(State::HalfOpen));
+
+		cb.record_many_successes(3);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		assert_eq!(cb.try_close(), Ok(()));
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.get_buffer().evaluated_events(), 0);
+	}
+
+	#[test]
+	fn try_close_consecutive_test() {
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				trip_on_consecutive: Some(3),
+				..Settings::default()
+			},
+			ManualClock::default(),
+		);
+		cb.record_many_failures(3);
+		let opened = cb.get_state();
+		assert!(matches!(opened, State::Open(_)));
+
+		// too few events for an error rate but the circuit stays open
+		assert_eq!(cb.get_error_rate(), 0.0);
+		assert_eq!(cb.try_close(), Err(opened));
+		assert_eq!(cb.get_state(), opened);
+	}
+
+	#[test]
+	fn span_error_rates_test() {
+		let mut cb = CircuitBreaker {
+			buffer: RingBuffer::new(5),
+			..CircuitBreaker::default()
+		};
+		cb.buffer.add_failures(1);
+		cb.buffer.add_successes(3);
+		cb.buffer.advance(1);
+		cb.buffer.add_failures(2);
+		cb.buffer.add_successes(2);
+		cb.buffer.advance(2);
+		cb.buffer.add_failures(4);
+		cb.buffer.advance(1);
+		cb.buffer.add_failures(1);
+
+		assert_eq!(cb.span_error_rates(), vec![25.0, 50.0, 0.0, 100.0, 0.0]);
+	}
+
+	#[test]
+	fn probe_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				include_current_span: true,
+				trial_success_required: 2,
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		let result = cb.probe::<(), (), _>(|| unreachable!("closed circuits aren't probed"));
+		assert_eq!(
+			result,
+			ProbeResult {
+				ran: false,
+				closed: false,
+				result: None,
+			}
+		);
+
+		cb.record_bool(false);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		clock.advance(cb.get_settings().retry_timeout);
+		assert_eq!(
+			cb.probe::<u8, (), _>(|| Ok(1)),
+			ProbeResult {
+				ran: true,
+				closed: false,
+				result: Some(Ok(1)),
+			}
+		);
+		assert_eq!(
+			cb.probe::<u8, (), _>(|| Ok(2)),
+			ProbeResult {
+				ran: true,
+				closed: true,
+				result: Some(Ok(2)),
+			}
+		);
+		assert_eq!(cb.get_state(), State::Closed);
+
+		cb.record_bool(false);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		clock.advance(cb.get_settings().retry_timeout);
+		assert_eq!(
+			cb.probe::<u8, &str, _>(|| Err("down")),
+			ProbeResult {
+				ran: true,
+				closed: false,
+				result: Some(Err("down")),
+			}
+		);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert!(!cb.probe::<u8, &str, _>(|| Ok(3)).ran);
+	}
+
+	#[test]
+	fn explain_state_test() {
+		let clock = ManualClock::default();
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				buffer_span_duration,
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		cb.record_many_successes(3);
+		cb.record_many_failures(1);
+		clock.advance(buffer_span_duration);
+
+		assert_eq!(
+			cb.explain_state(),
+			StateExplanation {
+				state: State::Closed,
+				error_rate: 0.0,
+				evaluated_events: 4,
+				min_eval_size: 100,
+				trial_success: 0,
+				trial_success_required: 20,
+				time_until_transition: None,
+				open_reason: None,
+			}
+		);
+	}
+
+	#[test]
+	fn explain_state_current_span_test() {
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 2,
+				include_current_span: true,
 				..Settings::default()
-			})
-			.buffer
-			.get_size(),
-			10
+			},
+			ManualClock::default(),
+		);
+		cb.record_many_failures(2);
+
+		// the events counted are the ones the error rate is calculated from
+		let explanation = cb.explain_state();
+		assert!(matches!(explanation.state, State::Open(_)));
+		assert_eq!(explanation.error_rate, 100.0);
+		assert_eq!(explanation.evaluated_events, 2);
+		assert_eq!(cb.buffer_utilization(), 1.0);
+	}
+
+	#[test]
+	fn merge_metrics_from_test() {
+		let settings = Settings {
+			min_eval_size: 1,
+			error_threshold: 100.0,
+			include_current_span: true,
+			..Settings::default()
+		};
+		let mut reporting = CircuitBreaker::new(settings);
+		let mut shard_a = CircuitBreaker::new(settings);
+		let mut shard_b = CircuitBreaker::new(settings);
+		shard_a.record_many_successes(3);
+		shard_a.record_many_failures(1);
+		shard_b.record_many_successes(1);
+		shard_b.record_many_failures(3);
+		assert_eq!(shard_a.get_error_rate(), 25.0);
+
+		assert!(reporting.merge_metrics_from(&shard_a));
+		assert!(reporting.merge_metrics_from(&shard_b));
+		assert_eq!(reporting.get_error_rate(), 50.0);
+		assert_eq!(reporting.lifetime_totals(), (4, 4));
+		assert_eq!(reporting.get_state(), State::Closed);
+
+		let other_size = CircuitBreaker::new(Settings {
+			buffer_size: 3,
+			..settings
+		});
+		assert!(!reporting.merge_metrics_from(&other_size));
+		assert_eq!(reporting.lifetime_totals(), (4, 4));
+	}
+
+	#[test]
+	fn eval_debounce_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				retry_timeout: Duration::from_millis(10),
+				eval_debounce: Some(Duration::from_secs(1)),
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		cb.transition_to(State::Open(clock.now()), clock.now());
+		let transitions = Arc::new(Mutex::new(Vec::new()));
+		let sink = transitions.clone();
+		cb.on_transition(move |from, to| sink.lock().unwrap().push((from, to)));
+
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		// past the retry timeout but within the debounce the last result is kept
+		clock.advance(Duration::from_millis(10));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert!(transitions.lock().unwrap().is_empty());
+
+		clock.advance(Duration::from_millis(990));
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		assert_eq!(transitions.lock().unwrap().len(), 1);
+	}
+
+	#[test]
+	fn eval_debounce_record_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				trip_on_consecutive: Some(2),
+				eval_debounce: Some(Duration::from_secs(1)),
+				..Settings::default()
+			},
+			clock.clone(),
 		);
+		assert_eq!(cb.get_state(), State::Closed);
+
+		// recording evaluates the state even within the debounce
+		cb.record_bool(false);
+		cb.record_bool(false);
+		cb.record_bool(false);
+		assert!(matches!(cb.peek_state(), State::Open(_)));
+	}
+
+	#[test]
+	fn on_transition_on_record_test() {
+		let transitions = Arc::new(Mutex::new(Vec::new()));
+		let recorded = Arc::new(Mutex::new(Vec::new()));
+		let mut cb = CircuitBreaker::default();
+		let sink = transitions.clone();
+		cb.on_transition(move |from, to| sink.lock().unwrap().push((from, to)));
+		let sink = recorded.clone();
+		cb.on_record(move |successes, failures| sink.lock().unwrap().push((successes, failures)));
+
+		cb.record::<(), ()>(Ok(()));
+		cb.record_many_failures(3);
+		cb.transition_to(State::HalfOpen, Instant::now());
+		cb.transition_to(State::Closed, Instant::now());
+		assert_eq!(*recorded.lock().unwrap(), vec![(1, 0), (0, 3)]);
+		assert_eq!(*transitions.lock().unwrap(), vec![(State::Closed, State::HalfOpen), (State::HalfOpen, State::Closed)]);
+	}
+
+	#[test]
+	fn on_open_emit_once_test() {
+		let opened = Arc::new(AtomicUsize::new(0));
+		let retry_timeout = Duration::from_millis(100);
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			retry_timeout,
+			buffer_span_duration,
+			trial_success_required: 1,
+			..Settings::default()
+		});
+		let counter = opened.clone();
+		cb.on_open_emit_once(move || {
+			counter.fetch_add(1, Ordering::SeqCst);
+		});
+
+		cb.record::<(), &str>(Err(""));
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		for _ in 0..10 {
+			assert!(matches!(cb.get_state(), State::Open(_)));
+			cb.record::<(), &str>(Err(""));
+		}
+		assert_eq!(opened.load(Ordering::SeqCst), 1);
+
+		// HalfOpen -> Open is a new episode
+		std::thread::sleep(retry_timeout);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.record::<(), &str>(Err(""));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(opened.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn transition_history_test() {
+		let clock = ManualClock::default();
+		let settings = Settings {
+			min_eval_size: 1,
+			include_current_span: true,
+			trial_success_required: 1,
+			transition_log_size: 3,
+			..Settings::default()
+		};
+		let mut cb = CircuitBreaker::new_with_clock(settings, clock.clone());
+		assert!(cb.transition_history().is_empty());
+
+		let opened_at = clock.now();
+		cb.record_bool(false);
+		assert_eq!(cb.get_state(), State::Open(opened_at));
+		clock.advance(settings.retry_timeout);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		assert_eq!(cb.transition_history().len(), 2);
+		assert_eq!(cb.transition_history()[0].from, State::Closed);
+		assert_eq!(cb.transition_history()[0].to, State::Open(opened_at));
+		assert_eq!(cb.transition_history()[1].from, State::Open(opened_at));
+		assert_eq!(cb.transition_history()[1].to, State::HalfOpen);
+
+		cb.record_bool(true);
+		clock.advance(Duration::from_secs(1));
+		let reopened_at = clock.now();
+		cb.record_bool(false);
+		assert_eq!(cb.get_state(), State::Open(reopened_at));
+		let history: Vec<(State, State)> = cb.transition_history().iter().map(|record| (record.from, record.to)).collect();
 		assert_eq!(
-			CircuitBreaker::new(Settings {
-				buffer_size: 666,
-				min_eval_size: 5,
-				error_threshold: 99.99,
-				retry_timeout: Duration::from_millis(20),
-				buffer_span_duration: Duration::from_millis(999),
-				trial_success_required: 42,
-			})
-			.settings,
+			history,
+			vec![
+				(State::Open(opened_at), State::HalfOpen),
+				(State::HalfOpen, State::Closed),
+				(State::Closed, State::Open(reopened_at)),
+			]
+		);
+		assert!(cb.transition_history()[0].at < cb.transition_history()[2].at);
+
+		let mut cb = CircuitBreaker::new_with_clock(
 			Settings {
-				buffer_size: 666,
-				min_eval_size: 5,
-				error_threshold: 99.99,
-				retry_timeout: Duration::from_millis(20),
-				buffer_span_duration: Duration::from_millis(999),
-				trial_success_required: 42,
+				transition_log_size: 0,
+				..settings
+			},
+			clock.clone(),
+		);
+		cb.record_bool(false);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert!(cb.transition_history().is_empty());
+	}
+
+	#[test]
+	fn close_cooldown_test() {
+		let close_cooldown = Duration::from_millis(300);
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			trial_success_required: 1,
+			buffer_span_duration,
+			close_cooldown,
+			..Settings::default()
+		});
+		cb.state = State::HalfOpen;
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(cb.get_state(), State::Closed);
+
+		// residual errors right after closing don't open the circuit
+		cb.record_many_failures(5);
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		assert_eq!(cb.get_error_rate(), 100.0);
+		assert_eq!(cb.get_state(), State::Closed);
+
+		std::thread::sleep(close_cooldown);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+	}
+
+	#[test]
+	fn trial_decay_interval_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				trial_success_required: 3,
+				trial_decay_interval: Some(Duration::from_secs(10)),
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		cb.transition_to(State::HalfOpen, clock.now());
+
+		cb.record::<(), ()>(Ok(()));
+		clock.advance(Duration::from_secs(5));
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_trial_success(), 2);
+
+		// both stale successes decay after 2 intervals without a success
+		clock.advance(Duration::from_secs(25));
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_trial_success(), 1);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+
+		cb.record::<(), ()>(Ok(()));
+		clock.advance(Duration::from_secs(9));
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_state(), State::Closed);
+	}
+
+	#[test]
+	fn post_recovery_sensitive_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				post_recovery_sensitive: Duration::from_secs(10),
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		cb.transition_to(State::HalfOpen, clock.now());
+		cb.transition_to(State::Closed, clock.now());
+
+		clock.advance(Duration::from_secs(9));
+		cb.record::<(), ()>(Err(()));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.get_open_reason(), Some(OpenReason::TrialFailed));
+
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				post_recovery_sensitive: Duration::from_secs(10),
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		cb.transition_to(State::HalfOpen, clock.now());
+		cb.transition_to(State::Closed, clock.now());
+
+		clock.advance(Duration::from_secs(10));
+		cb.record::<(), ()>(Err(()));
+		cb.record_many_failures(1);
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.get_open_reason(), None);
+	}
+
+	#[test]
+	fn post_recovery_sensitive_record_window_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				include_current_span: true,
+				trial_success_required: 1,
+				post_recovery_sensitive: Duration::from_secs(10),
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		cb.record_bool(false);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		clock.advance(cb.get_settings().retry_timeout);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.record_bool(true);
+		assert_eq!(cb.peek_state(), State::Closed);
+
+		clock.advance(Duration::from_secs(5));
+		cb.record_window(clock.now(), [Ok::<(), ()>(()), Ok(()), Err(())]);
+		assert!(matches!(cb.peek_state(), State::Open(_)));
+		assert_eq!(cb.get_open_reason(), Some(OpenReason::TrialFailed));
+	}
+
+	#[test]
+	fn quantized_state_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				include_current_span: true,
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		assert_eq!(cb.quantized_state(), 0);
+		assert_eq!(State::from_u8(cb.quantized_state()), Some(State::Closed));
+
+		cb.record_bool(false);
+		assert_eq!(cb.quantized_state(), 2);
+		assert!(matches!(State::from_u8(cb.quantized_state()), Some(State::Open(_))));
+
+		clock.advance(cb.get_settings().retry_timeout);
+		assert_eq!(cb.quantized_state(), 1);
+		assert_eq!(State::from_u8(cb.quantized_state()), Some(State::HalfOpen));
+
+		assert_eq!(State::from_u8(3), None);
+	}
+
+	#[test]
+	fn severity_test() {
+		assert!(State::Closed.severity() < State::HalfOpen.severity());
+		assert!(State::HalfOpen.severity() < State::Open(Instant::now()).severity());
+	}
+
+	#[test]
+	fn replay_determinism_test() {
+		let settings = Settings {
+			buffer_size: 4,
+			buffer_span_duration: Duration::from_secs(1),
+			min_eval_size: 2,
+			retry_timeout: Duration::from_secs(3),
+			trial_success_required: 2,
+			..Settings::default()
+		};
+		// (success, tick in ms after the event)
+		let script = [
+			(true, 300),
+			(false, 400),
+			(true, 900),
+			(false, 200),
+			(false, 100),
+			(false, 1200),
+			(true, 3000),
+			(true, 500),
+			(true, 0),
+			(false, 1700),
+			(true, 2500),
+		];
+
+		let clock = ManualClock::default();
+		let mut a = CircuitBreaker::new_with_clock(settings, clock.clone());
+		let mut b = CircuitBreaker::new_with_clock(settings, clock.clone());
+		for (success, tick) in script {
+			for cb in [&mut a, &mut b] {
+				if success {
+					cb.record::<(), ()>(Ok(()));
+				} else {
+					cb.record::<(), ()>(Err(()));
+				}
+			}
+			clock.advance(Duration::from_millis(tick));
+			assert_eq!(a.get_state(), b.get_state());
+		}
+
+		assert!(!a.transition_history().is_empty());
+		assert_eq!(a.summary_json(), b.summary_json());
+		assert_eq!(a.clone_buffer(), b.clone_buffer());
+		assert_eq!(a.transition_history(), b.transition_history());
+	}
+
+	#[test]
+	fn recover_on_error_rate_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			trial_success_required: 10,
+			recover_on_error_rate: Some(20.0),
+			..Settings::default()
+		});
+		cb.state = State::HalfOpen;
+
+		// a failure no longer opens the circuit right away
+		cb.record::<(), ()>(Err(()));
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.record_many_successes(8);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		// a 10% error rate over the sample of 10 events closes the circuit
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.trial_success, 0);
+		assert_eq!(cb.trial_failure, 0);
+
+		cb.state = State::HalfOpen;
+		cb.record_many_failures(2);
+		cb.record_many_successes(7);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		// a 20% error rate is not below the threshold
+		cb.record::<(), ()>(Ok(()));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.trial_success, 0);
+		assert_eq!(cb.trial_failure, 0);
+	}
+
+	#[test]
+	fn denominator_policy_test() {
+		let settings = Settings {
+			buffer_size: 4,
+			min_eval_size: 1,
+			error_threshold: 55.0,
+			..Settings::default()
+		};
+		let mut cb = CircuitBreaker::new(settings);
+		let buffer = cb.get_buffer();
+		buffer.add_failures(6);
+		buffer.advance(1);
+		buffer.add_successes(4);
+		buffer.advance(1);
+		buffer.add_failures(1);
+		buffer.add_successes(1);
+		buffer.advance(1);
+
+		for (denominator_policy, rate) in [
+			(DenominatorPolicy::WholeWindow, 58.33),
+			(DenominatorPolicy::TrailingSpan, 50.0),
+			(DenominatorPolicy::LastN(2), 16.67),
+			(DenominatorPolicy::LastN(3), 58.33),
+		] {
+			cb.replace_settings_preserving_state(Settings {
+				denominator_policy,
+				..settings
+			});
+			assert_eq!(cb.get_error_rate(), rate, "{denominator_policy:?}");
+		}
+
+		// evaluate_state trips on the rate of the configured spans only
+		cb.replace_settings_preserving_state(Settings {
+			denominator_policy: DenominatorPolicy::TrailingSpan,
+			..settings
+		});
+		assert_eq!(cb.get_state(), State::Closed);
+		cb.replace_settings_preserving_state(settings);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+	}
+
+	#[test]
+	fn capacity_events_test() {
+		let cb = CircuitBreaker::new(Settings {
+			buffer_size: 5,
+			buffer_span_duration: Duration::from_secs(10),
+			..Settings::default()
+		});
+		assert_eq!(cb.capacity_events(0), 0);
+		assert_eq!(cb.capacity_events(1), 50);
+		assert_eq!(cb.capacity_events(200), 10_000);
+
+		let cb = CircuitBreaker::new(Settings {
+			buffer_size: 3,
+			buffer_span_duration: Duration::from_millis(500),
+			..Settings::default()
+		});
+		assert_eq!(cb.capacity_events(3), 4);
+
+		let cb = CircuitBreaker::new(Settings {
+			buffer_size: 4,
+			window_mode: WindowMode::SpanEvents(25),
+			..Settings::default()
+		});
+		assert_eq!(cb.capacity_events(1), 100);
+		assert_eq!(cb.capacity_events(1000), 100);
+
+		let cb = CircuitBreaker::new(Settings {
+			window_mode: WindowMode::RollingLog(42),
+			..Settings::default()
+		});
+		assert_eq!(cb.capacity_events(7), 42);
+	}
+
+	#[test]
+	fn buffer_utilization_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 10,
+			..Settings::default()
+		});
+		assert_eq!(cb.buffer_utilization(), 0.0);
+
+		// the current node isn't evaluated yet
+		cb.get_buffer().add_successes(5);
+		assert_eq!(cb.buffer_utilization(), 0.0);
+		cb.get_buffer().advance(1);
+		assert_eq!(cb.buffer_utilization(), 0.5);
+
+		cb.get_buffer().add_failures(20);
+		cb.get_buffer().advance(1);
+		assert_eq!(cb.buffer_utilization(), 1.0);
+
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 4,
+			window_mode: WindowMode::RollingLog(10),
+			..Settings::default()
+		});
+		cb.record_many_successes(2);
+		assert_eq!(cb.buffer_utilization(), 0.5);
+	}
+
+	#[test]
+	fn record_bool_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let settings = Settings {
+			min_eval_size: 1,
+			trial_success_required: 2,
+			buffer_span_duration,
+			..Settings::default()
+		};
+		let outcomes = [true, false, false, true, false];
+
+		let mut generic = CircuitBreaker::new(settings);
+		let mut plain = CircuitBreaker::new(settings);
+		for success in outcomes {
+			if success {
+				generic.record::<u8, String>(Ok(1));
+			} else {
+				generic.record::<u8, String>(Err(String::from("error")));
+			}
+			plain.record_bool(success);
+		}
+		assert_eq!(generic.clone_buffer(), plain.clone_buffer());
+
+		generic.state = State::HalfOpen;
+		plain.state = State::HalfOpen;
+		generic.record::<(), ()>(Ok(()));
+		plain.record_bool(true);
+		assert_eq!(generic.trial_success, plain.trial_success);
+		generic.record::<(), ()>(Err(()));
+		plain.record_bool(false);
+		assert!(matches!(generic.state, State::Open(_)));
+		assert!(matches!(plain.state, State::Open(_)));
+	}
+
+	#[test]
+	fn include_current_span_test() {
+		let settings = Settings {
+			min_eval_size: 10,
+			..Settings::default()
+		};
+		let mut cb = CircuitBreaker::new(settings);
+		cb.get_buffer().add_failures(10);
+		cb.get_buffer().add_successes(10);
+		assert_eq!(cb.get_error_rate(), 0.0);
+		assert_eq!(cb.get_state(), State::Closed);
+
+		cb.replace_settings_preserving_state(Settings {
+			include_current_span: true,
+			..settings
+		});
+		assert_eq!(cb.get_error_rate(), 50.0);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+	}
+
+	#[test]
+	fn drain_metrics_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			buffer_span_duration,
+			..Settings::default()
+		});
+		cb.record::<(), ()>(Ok(()));
+		cb.record_many_failures(3);
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		// only checking isn't a rejection
+		assert!(!cb.is_call_permitted());
+		assert!(cb.call(|| Ok::<(), ()>(())).is_err());
+		assert!(cb.acquire().is_err());
+		cb.record::<(), ()>(Ok(())); // ignored while open
+
+		assert_eq!(
+			cb.drain_metrics(),
+			MetricsSnapshot {
+				events_recorded: 4,
+				rejected_count: 2,
+				transition_count: 1,
 			}
 		);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.drain_metrics(), MetricsSnapshot::default());
 	}
 
 	#[test]
-	fn get_state_test() {
-		assert_eq!(CircuitBreaker::new(Settings::default()).get_state(), State::Closed);
-	}
-
-	#[test]
-	fn advance_buffer_for_time_test() {
-		let buffer_span_duration = Duration::from_secs(1);
-		let last_record = Instant::now();
-		let mut cb = CircuitBreaker {
-			buffer: RingBuffer::new(3),
-			state: State::Closed,
-			last_record,
-			start_time: Instant::now(),
-			trial_success: 0,
-			settings: Settings {
+	fn record_window_test() {
+		let buffer_span_duration = Duration::from_secs(10);
+		let clock = ManualClock::default();
+		let start = clock.now();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
 				buffer_span_duration,
 				..Settings::default()
 			},
-		};
+			clock.clone(),
+		);
 
+		cb.record_window(start, [Ok(()), Err(())]);
+		clock.advance(buffer_span_duration);
+		cb.record_window(start + buffer_span_duration, vec![Ok::<(), ()>(()), Ok(()), Ok(())]);
 		assert_eq!(
 			cb.get_buffer().get_node_info(0),
 			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
+				failure_count: 1,
+				success_count: 1,
 			}
 		);
 		assert_eq!(
 			cb.get_buffer().get_node_info(1),
 			NodeInfo {
 				failure_count: 0,
-				success_count: 0,
+				success_count: 3,
 			}
 		);
-		assert_eq!(
-			cb.get_buffer().get_node_info(2),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+		assert_eq!(cb.drain_metrics().events_recorded, 5);
+	}
+
+	#[test]
+	fn record_window_clamp_test() {
+		let buffer_span_duration = Duration::from_secs(10);
+		let clock = ManualClock::default();
+		let start = clock.now();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				buffer_span_duration,
+				..Settings::default()
+			},
+			clock.clone(),
 		);
+
+		// a future span is recorded as of now instead of advancing the buffer
+		cb.record_window(start + buffer_span_duration * 3, [Err::<(), ()>(())]);
 		assert_eq!(cb.get_buffer().get_cursor(), 0);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		assert_eq!(
-			cb.get_buffer().get_node_info(0),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 2,
-			}
-		);
-		assert_eq!(
-			cb.get_buffer().get_node_info(1),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
-		);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+
+		// a span before the current one is recorded into the current span
+		clock.advance(buffer_span_duration * 2);
+		cb.record::<(), ()>(Ok(()));
+		cb.record_window(start, [Err::<(), ()>(()), Err(())]);
+		assert_eq!(cb.get_buffer().get_cursor(), 2);
 		assert_eq!(
 			cb.get_buffer().get_node_info(2),
 			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
+				failure_count: 2,
+				success_count: 1,
 			}
 		);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+	}
 
-		cb.advance_buffer_for_time(last_record);
-		assert_eq!(cb.get_buffer().get_cursor(), 0);
-		assert_eq!(
-			cb.get_buffer().get_node_info(0),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 2,
-			}
+	#[test]
+	fn effective_retry_timeout_test() {
+		let retry_timeout = Duration::from_secs(30);
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				retry_timeout,
+				min_eval_size: 1,
+				include_current_span: true,
+				..Settings::default()
+			},
+			ManualClock::default(),
 		);
-		assert_eq!(
-			cb.get_buffer().get_node_info(1),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
+		assert_eq!(cb.effective_retry_timeout(), retry_timeout);
+
+		// the probe time and the countdown are derived from it
+		cb.record_bool(false);
+		let State::Open(opened_at) = cb.get_state() else {
+			panic!("the circuit should be open");
+		};
+		assert_eq!(cb.next_probe_at(), Some(opened_at + cb.effective_retry_timeout()));
+		assert_eq!(cb.time_until_retry(), Some(cb.effective_retry_timeout()));
+	}
+
+	#[test]
+	fn retry_after_secs_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				retry_timeout: Duration::from_secs(5),
+				..Settings::default()
+			},
+			clock.clone(),
 		);
-		assert_eq!(
-			cb.get_buffer().get_node_info(2),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
+		assert_eq!(cb.time_until_retry(), None);
+		assert_eq!(cb.retry_after_secs(), None);
+
+		cb.transition_to(State::Open(clock.now()), clock.now());
+		assert_eq!(cb.retry_after_secs(), Some(5));
+		clock.advance(Duration::from_millis(1));
+		assert_eq!(cb.time_until_retry(), Some(Duration::from_millis(4999)));
+		assert_eq!(cb.retry_after_secs(), Some(5));
+		clock.advance(Duration::from_millis(3999));
+		assert_eq!(cb.retry_after_secs(), Some(1));
+		clock.advance(Duration::from_millis(700));
+		assert_eq!(cb.retry_after_secs(), Some(1));
+		clock.advance(Duration::from_millis(300));
+		assert_eq!(cb.retry_after_secs(), Some(0));
+	}
+
+	#[test]
+	fn next_probe_at_test() {
+		let retry_timeout = Duration::from_secs(30);
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				retry_timeout,
+				..Settings::default()
+			},
+			clock.clone(),
 		);
+		assert_eq!(cb.next_probe_at(), None);
+		assert!(!cb.attempt_probe());
 
-		cb.advance_buffer_for_time(last_record + buffer_span_duration);
-		assert_eq!(cb.get_buffer().get_cursor(), 1);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Err(""));
-		cb.record::<(), &str>(Err(""));
-		cb.record::<(), &str>(Ok(()));
+		let opened_at = clock.now();
+		cb.transition_to(State::Open(opened_at), opened_at);
+		assert_eq!(cb.next_probe_at(), Some(opened_at + retry_timeout));
+
+		clock.advance(Duration::from_secs(29));
+		assert!(!cb.attempt_probe());
+		clock.advance(Duration::from_secs(1));
+		assert!(cb.attempt_probe());
+		assert_eq!(cb.next_probe_at(), None);
+	}
+
+	#[test]
+	fn parse_duration_test() {
+		assert_eq!(parse_duration("30"), Some(Duration::from_secs(30)));
+		assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+		assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+		assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+		assert_eq!(parse_duration(" 2h "), Some(Duration::from_secs(7200)));
+		assert_eq!(parse_duration("2d"), None);
+		assert_eq!(parse_duration("-5s"), None);
+		assert_eq!(parse_duration("1.5"), Some(Duration::from_millis(1500)));
+		assert_eq!(parse_duration("0.25s"), Some(Duration::from_millis(250)));
+		assert_eq!(parse_duration("2.5ms"), Some(Duration::from_micros(2500)));
+		assert_eq!(parse_duration("0.0000000015"), Some(Duration::from_nanos(1)));
+		assert_eq!(parse_duration("1.5.5"), None);
+		assert_eq!(parse_duration(".5"), None);
+		assert_eq!(parse_duration("NaN"), None);
+		assert_eq!(parse_duration("s"), None);
+		assert_eq!(parse_duration(""), None);
+	}
+
+	#[test]
+	fn from_lookup_test() {
+		let vars = HashMap::from([
+			("CB_BUFFER_SIZE", "8"),
+			("CB_BUFFER_SPAN_DURATION", "30s"),
+			("CB_ERROR_THRESHOLD", "12.5"),
+			("CB_RETRY_TIMEOUT", "500ms"),
+		]);
 		assert_eq!(
-			cb.get_buffer().get_node_info(0),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 2,
-			}
+			Settings::from_lookup(|name| vars.get(name).map(|value| value.to_string())),
+			Ok(Settings {
+				buffer_size: 8,
+				buffer_span_duration: Duration::from_secs(30),
+				error_threshold: 12.5,
+				retry_timeout: Duration::from_millis(500),
+				..Settings::default()
+			})
 		);
+		assert_eq!(Settings::from_lookup(|_| None), Ok(Settings::default()));
 		assert_eq!(
-			cb.get_buffer().get_node_info(1),
-			NodeInfo {
-				failure_count: 2,
-				success_count: 2,
-			}
+			Settings::from_lookup(|name| (name == "CB_MIN_EVAL_SIZE").then(|| String::from("lots"))),
+			Err(SettingsError::InvalidEnvVar {
+				name: String::from("CB_MIN_EVAL_SIZE"),
+				value: String::from("lots"),
+			})
 		);
 		assert_eq!(
-			cb.get_buffer().get_node_info(2),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
+			Settings::from_lookup(|name| (name == "CB_BUFFER_SIZE").then(|| String::from("0"))),
+			Err(SettingsError::ZeroBufferSize)
 		);
+	}
+
+	#[test]
+	fn from_env_test() {
+		// the only test touching the process environment
+		std::env::set_var("CB_TRIAL_SUCCESS_REQUIRED", "3");
+		std::env::set_var("CB_RETRY_TIMEOUT", "2m");
+		let settings = Settings::from_env();
+		std::env::remove_var("CB_TRIAL_SUCCESS_REQUIRED");
+		std::env::remove_var("CB_RETRY_TIMEOUT");
 
-		cb.advance_buffer_for_time(last_record + buffer_span_duration + buffer_span_duration);
-		assert_eq!(cb.get_buffer().get_cursor(), 2);
-		cb.record::<(), &str>(Err(""));
-		cb.record::<(), &str>(Ok(()));
-		assert_eq!(
-			cb.get_buffer().get_node_info(0),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 2,
-			}
-		);
 		assert_eq!(
-			cb.get_buffer().get_node_info(1),
-			NodeInfo {
-				failure_count: 2,
-				success_count: 2,
-			}
+			settings,
+			Ok(Settings {
+				trial_success_required: 3,
+				retry_timeout: Duration::from_secs(120),
+				..Settings::default()
+			})
 		);
+	}
+
+	#[test]
+	fn record_if_closed_test() {
+		let mut cb = CircuitBreaker::default();
+		assert!(cb.record_if_closed::<(), ()>(Ok(())));
+		assert!(cb.record_if_closed::<(), ()>(Err(())));
 		assert_eq!(
-			cb.get_buffer().get_node_info(2),
+			cb.get_buffer().get_node_info(0),
 			NodeInfo {
 				failure_count: 1,
 				success_count: 1,
 			}
 		);
 
-		cb.advance_buffer_for_time(
-			last_record
-				+ buffer_span_duration
-				+ buffer_span_duration
-				+ buffer_span_duration
-				+ buffer_span_duration
-				+ buffer_span_duration,
-		);
-		assert_eq!(cb.get_buffer().get_cursor(), 2);
-		assert_eq!(
-			cb.get_buffer().get_node_info(0),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
-		);
-		assert_eq!(
-			cb.get_buffer().get_node_info(1),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
-		);
-		assert_eq!(
-			cb.get_buffer().get_node_info(2),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
-		);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Err(""));
-		cb.record::<(), &str>(Err(""));
-		assert_eq!(
-			cb.get_buffer().get_node_info(0),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
-		);
-		assert_eq!(
-			cb.get_buffer().get_node_info(1),
-			NodeInfo {
-				failure_count: 0,
-				success_count: 0,
-			}
+		cb.state = State::HalfOpen;
+		assert!(!cb.record_if_closed::<(), ()>(Ok(())));
+		assert!(!cb.record_if_closed::<(), ()>(Err(())));
+		assert_eq!(cb.state, State::HalfOpen);
+		assert_eq!(cb.trial_success, 0);
+
+		let opened_at = Instant::now();
+		cb.state = State::Open(opened_at);
+		assert!(!cb.record_if_closed::<(), ()>(Ok(())));
+		assert_eq!(cb.state, State::Open(opened_at));
+		assert_eq!(cb.get_buffer().get_node_info(0).total(), 2);
+	}
+
+	#[test]
+	fn trip_on_consecutive_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 100,
+			trip_on_consecutive: Some(3),
+			..Settings::default()
+		});
+		cb.record::<(), ()>(Err(()));
+		cb.record::<(), ()>(Err(()));
+		cb.record::<(), ()>(Ok(()));
+		cb.record::<(), ()>(Err(()));
+		cb.record::<(), ()>(Err(()));
+		assert_eq!(cb.get_state(), State::Closed);
+		cb.record::<(), ()>(Err(()));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+
+		let mut cb = CircuitBreaker::new(Settings {
+			trip_on_consecutive: Some(5),
+			..Settings::default()
+		});
+		cb.record_many_failures(5);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+
+		// failures recorded before it was enabled count too
+		let settings = Settings {
+			min_eval_size: 100,
+			..Settings::default()
+		};
+		let mut cb = CircuitBreaker::new(settings);
+		cb.record_many_failures(2);
+		assert_eq!(cb.get_state(), State::Closed);
+		cb.replace_settings_preserving_state(Settings {
+			trip_on_consecutive: Some(2),
+			..settings
+		});
+		assert!(matches!(cb.get_state(), State::Open(_)));
+	}
+
+	#[test]
+	fn spans_test() {
+		let buffer_span_duration = Duration::from_secs(10);
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				buffer_size: 4,
+				buffer_span_duration,
+				..Settings::default()
+			},
+			clock.clone(),
 		);
-		assert_eq!(
-			cb.get_buffer().get_node_info(2),
-			NodeInfo {
-				failure_count: 2,
-				success_count: 1,
-			}
+		cb.record::<(), ()>(Err(()));
+		clock.advance(Duration::from_secs(23));
+		cb.record::<(), ()>(Ok(()));
+
+		let now = clock.now();
+		let mut spans = cb.spans(now);
+		assert_eq!(spans.len(), 4);
+		assert_eq!(spans.iter().filter(|span| span.active).count(), 1);
+		assert!(spans[2].active);
+		assert_eq!(spans[2].start, now - Duration::from_secs(3));
+		assert_eq!(spans[2].info.success_count, 1);
+		assert_eq!(spans[0].info.failure_count, 1);
+
+		spans.sort_by_key(|span| span.start);
+		assert_eq!(spans.iter().map(|span| span.index).collect::<Vec<_>>(), vec![3, 0, 1, 2]);
+		for pair in spans.windows(2) {
+			assert_eq!(pair[0].end, pair[1].start);
+			assert_eq!(pair[0].end - pair[0].start, buffer_span_duration);
+		}
+	}
+
+	#[test]
+	fn spans_stale_cursor_test() {
+		let buffer_span_duration = Duration::from_secs(10);
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				buffer_size: 4,
+				buffer_span_duration,
+				..Settings::default()
+			},
+			clock.clone(),
 		);
+		let start = clock.now();
+		cb.record::<(), ()>(Err(()));
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_buffer().get_cursor(), 0);
+
+		// nothing advanced the cursor since but the ranges are as of now
+		let now = start + Duration::from_secs(23);
+		let spans = cb.spans(now);
+		assert!(spans[2].active);
+		assert_eq!(spans[2].start, start + Duration::from_secs(20));
+		assert_eq!(spans[0].start, start);
+		assert_eq!(spans[0].end, start + buffer_span_duration);
+		assert_eq!(spans[0].info.total(), 2);
+		assert_eq!(spans[1].info.total(), 0);
+
+		// once the cursor is stale for the whole buffer every span is empty
+		let spans = cb.spans(start + Duration::from_secs(45));
+		assert!(spans[0].active);
+		assert_eq!(spans[0].start, start + Duration::from_secs(40));
+		assert!(spans.iter().all(|span| span.info.total() == 0));
 	}
 
 	#[test]
-	fn record_test() {
-		let mut cb = CircuitBreaker::new(Settings::default());
+	fn record_fraction_test() {
+		let mut cb = CircuitBreaker::default();
+		cb.record_fraction(0.8);
 		assert_eq!(
-			cb.buffer.get_node_info(0),
+			cb.get_buffer().get_node_info(0),
 			NodeInfo {
-				success_count: 0,
-				failure_count: 0,
+				failure_count: 2,
+				success_count: 8,
 			}
 		);
-		cb.record::<(), &str>(Ok(()));
+
+		cb.record_fraction(1.7);
+		cb.record_fraction(f32::NAN);
 		assert_eq!(
-			cb.buffer.get_node_info(0),
+			cb.get_buffer().get_node_info(0),
 			NodeInfo {
-				success_count: 1,
-				failure_count: 0,
+				failure_count: 12,
+				success_count: 18,
 			}
 		);
-		cb.record::<(), &str>(Err(""));
-		assert_eq!(
-			cb.buffer.get_node_info(0),
-			NodeInfo {
-				success_count: 1,
-				failure_count: 1,
-			}
+	}
+
+	#[test]
+	fn record_fraction_half_open_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				include_current_span: true,
+				trial_success_required: 5,
+				..Settings::default()
+			},
+			clock.clone(),
 		);
+		cb.record_bool(false);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		clock.advance(cb.get_settings().retry_timeout);
+		assert_eq!(cb.get_state(), State::HalfOpen);
 
-		cb.state = State::Open(Instant::now());
+		cb.record_fraction(0.8);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.get_open_reason(), Some(OpenReason::TrialFailed));
+	}
+
+	#[test]
+	fn zero_retry_timeout_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			buffer_span_duration,
+			retry_timeout: Duration::ZERO,
+			trial_success_required: 2,
+			..Settings::default()
+		});
+		cb.record::<(), ()>(Err(()));
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		cb.evaluate_state();
+		assert!(matches!(cb.peek_state(), State::Open(_)));
+		assert_eq!(cb.time_until_retry(), Some(Duration::ZERO));
+
+		// the very next evaluation probes
+		cb.evaluate_state();
+		assert_eq!(cb.peek_state(), State::HalfOpen);
+		assert!(cb.is_call_permitted());
+
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.get_state(), State::Closed);
+	}
+
+	#[test]
+	fn peek_state_test() {
+		let retry_timeout = Duration::from_secs(1);
+		let opened_at = Instant::now() - retry_timeout;
+		let mut cb = CircuitBreaker {
+			state: State::Open(opened_at),
+			settings: Settings {
+				retry_timeout,
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+		assert_eq!(cb.peek_state(), State::Open(opened_at));
+		assert_eq!(cb.peek_state(), State::Open(opened_at));
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		assert_eq!(cb.peek_state(), State::HalfOpen);
+	}
+
+	#[test]
+	fn trip_priority_test() {
+		for (trip_priority, reason) in [
+			(TripKind::DEFAULT_PRIORITY, TripKind::ErrorRate),
+			([TripKind::ConsecutiveFailures, TripKind::ErrorRate, TripKind::SlowCalls], TripKind::ConsecutiveFailures),
+		] {
+			let mut cb = CircuitBreaker::new(Settings {
+				min_eval_size: 3,
+				include_current_span: true,
+				trip_on_consecutive: Some(3),
+				trip_priority,
+				..Settings::default()
+			});
+			assert_eq!(cb.get_open_reason(), None);
+			cb.record::<(), ()>(Err(()));
+			cb.record::<(), ()>(Err(()));
+			assert_eq!(cb.get_state(), State::Closed);
+
+			// both the error rate and the consecutive failures trip now
+			cb.record::<(), ()>(Err(()));
+			assert!(matches!(cb.get_state(), State::Open(_)));
+			assert_eq!(cb.get_open_reason(), Some(OpenReason::Trip(reason)));
+		}
+
+		// slow calls can take priority too
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 3,
+			include_current_span: true,
+			p95_slow_threshold: Some(Duration::from_millis(10)),
+			trip_priority: [TripKind::SlowCalls, TripKind::ErrorRate, TripKind::ConsecutiveFailures],
+			..Settings::default()
+		});
+		for _ in 0..3 {
+			cb.record_timed::<(), ()>(Err(()), Duration::from_secs(1));
+		}
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.get_open_reason(), Some(OpenReason::Trip(TripKind::SlowCalls)));
+
+		let mut cb = CircuitBreaker {
+			state: State::HalfOpen,
+			..CircuitBreaker::default()
+		};
+		cb.record::<(), ()>(Err(()));
+		assert_eq!(cb.get_open_reason(), Some(OpenReason::TrialFailed));
+	}
+
+	#[test]
+	fn extend_test() {
+		let mut cb = CircuitBreaker::default();
+		cb.extend(vec![true, false, true, true, false]);
 		assert_eq!(
-			cb.buffer.get_node_info(0),
+			cb.get_buffer().get_node_info(0),
 			NodeInfo {
-				success_count: 1,
-				failure_count: 1,
+				failure_count: 2,
+				success_count: 3,
 			}
 		);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Err(""));
+	}
+
+	#[test]
+	fn state_durations_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(Settings::default(), clock.clone());
+		clock.advance(Duration::from_secs(5));
 		assert_eq!(
-			cb.buffer.get_node_info(0),
-			NodeInfo {
-				success_count: 1,
-				failure_count: 1,
+			cb.state_durations(),
+			StateDurations {
+				closed: Duration::from_secs(5),
+				..StateDurations::default()
 			}
 		);
 
-		cb.state = State::HalfOpen;
+		cb.transition_to(State::Open(clock.now()), clock.now());
+		clock.advance(Duration::from_secs(3));
+		cb.transition_to(State::HalfOpen, clock.now());
+		clock.advance(Duration::from_secs(2));
+		cb.transition_to(State::Closed, clock.now());
+		clock.advance(Duration::from_secs(1));
+		cb.transition_to(State::Open(clock.now()), clock.now());
+		clock.advance(Duration::from_secs(4));
+
 		assert_eq!(
-			cb.buffer.get_node_info(0),
-			NodeInfo {
-				success_count: 1,
-				failure_count: 1,
+			cb.state_durations(),
+			StateDurations {
+				closed: Duration::from_secs(6),
+				open: Duration::from_secs(7),
+				half_open: Duration::from_secs(2),
 			}
 		);
-		assert_eq!(cb.trial_success, 0);
-		cb.record::<(), &str>(Ok(()));
 		assert_eq!(
-			cb.buffer.get_node_info(0),
-			NodeInfo {
-				success_count: 1,
-				failure_count: 1,
+			cb.state_entry_counts(),
+			StateEntryCounts {
+				closed: 1,
+				open: 2,
+				half_open: 1,
 			}
 		);
-		assert_eq!(cb.trial_success, 1);
-		cb.record::<(), &str>(Ok(()));
+	}
+
+	#[test]
+	fn lifetime_totals_test() {
+		let mut cb = CircuitBreaker::default();
+		cb.record::<(), ()>(Ok(()));
+		cb.record::<(), ()>(Err(()));
+		cb.record_many_successes(4);
+		cb.record_many_failures(2);
+		cb.drain_metrics();
+		assert_eq!(cb.lifetime_totals(), (5, 3));
+
+		cb.state = State::Open(Instant::now());
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.lifetime_totals(), (5, 3));
+	}
+
+	#[test]
+	fn atomic_buffer_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				error_threshold: 50.0,
+				include_current_span: true,
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		let atomic_buffer = cb.atomic_buffer();
+
+		let handles: Vec<_> = (0..4)
+			.map(|i| {
+				let atomic_buffer = Arc::clone(&atomic_buffer);
+				std::thread::spawn(move || {
+					for _ in 0..1000 {
+						if i == 0 {
+							atomic_buffer.record_success();
+						} else {
+							atomic_buffer.record_failure();
+						}
+					}
+				})
+			})
+			.collect();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+
+		assert_eq!(cb.lifetime_totals(), (0, 0));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.lifetime_totals(), (1000, 3000));
+		assert_eq!(cb.get_error_rate(), 75.0);
+
+		// events taken while open are dropped
+		atomic_buffer.record_failure();
+		clock.advance(Duration::from_secs(1));
+		cb.get_state();
+		assert_eq!(cb.lifetime_totals(), (1000, 3000));
+		assert!(atomic_buffer.take().has_no_events());
+	}
+
+	#[test]
+	fn atomic_buffer_spans_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				buffer_size: 3,
+				buffer_span_duration,
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		let atomic_buffer = cb.atomic_buffer();
+
+		atomic_buffer.record_failure();
+		clock.advance(buffer_span_duration);
+		cb.get_state();
+		atomic_buffer.record_success();
+		atomic_buffer.record_success();
+		clock.advance(buffer_span_duration);
+		cb.get_state();
+
+		// each event lands in the span it was recorded in
+		assert_eq!(atomic_buffer.get_cursor(), 2);
+		assert_eq!(cb.get_buffer().get_cursor(), 2);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+		assert_eq!(cb.get_buffer().get_node_info(1).success_count, 2);
+		assert_eq!(cb.lifetime_totals(), (2, 1));
+	}
+
+	#[test]
+	fn atomic_buffer_clone_test() {
+		let mut cb = CircuitBreaker::new_with_clock(Settings::default(), ManualClock::default());
+		let atomic_buffer = cb.atomic_buffer();
+		atomic_buffer.record_failure();
+
+		let mut clone = cb.clone();
+		assert_eq!(clone, cb);
+		clone.get_state();
+		assert_eq!(clone.lifetime_totals(), (0, 0));
+
+		// the clone gets its own atomic buffer
+		clone.atomic_buffer().record_success();
+		cb.get_state();
+		assert_eq!(cb.lifetime_totals(), (0, 1));
+	}
+
+	#[test]
+	fn is_cold_test() {
+		let mut cb = CircuitBreaker::default();
+		assert!(cb.is_cold());
+		cb.record::<(), ()>(Ok(()));
+		assert!(!cb.is_cold());
+	}
+
+	#[test]
+	fn estimate_recovery_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				trial_success_required: 20,
+				retry_timeout: Duration::from_secs(30),
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		assert_eq!(cb.estimate_recovery(2.0), Some(Duration::ZERO));
+
+		cb.transition_to(State::Open(clock.now()), clock.now());
+		clock.advance(Duration::from_secs(10));
+		assert_eq!(cb.trial_successes_remaining(), 20);
+		assert_eq!(cb.estimate_recovery(2.0), Some(Duration::from_secs(30)));
+
+		clock.advance(Duration::from_secs(20));
+		cb.record_many_successes(15);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		assert_eq!(cb.trial_successes_remaining(), 5);
+		assert_eq!(cb.estimate_recovery(2.0), Some(Duration::from_millis(2500)));
+		assert_eq!(cb.estimate_recovery(0.0), None);
+		assert_eq!(cb.estimate_recovery(f32::NAN), None);
+	}
+
+	#[test]
+	fn debug_test() {
+		let cb = CircuitBreaker::default();
 		assert_eq!(
-			cb.buffer.get_node_info(0),
-			NodeInfo {
-				success_count: 1,
-				failure_count: 1,
-			}
+			format!("{cb:?}"),
+			"CircuitBreaker { state: Closed, error_rate: 0.0, cursor: 0, buffer_size: 5, trial_success: 0, trial_success_required: 20 }"
 		);
-		assert_eq!(cb.trial_success, 2);
-		cb.record::<(), &str>(Err(""));
-		assert!(matches!(cb.state, State::Open(_)));
+		assert!(!format!("{cb:?}").contains("nodes"));
+		assert!(cb.debug_full().contains("nodes"));
+		assert!(cb.debug_full().contains("failure_count"));
+		assert!(cb.debug_full().contains("sticky_error_rate"));
+		assert_eq!(cb.debug_full(), format!("{cb:#?}"));
 	}
 
 	#[test]
-	fn record_timed_test() {
+	fn idle_reset_test() {
 		let buffer_span_duration = Duration::from_secs(1);
-		let mut cb = CircuitBreaker::new(Settings {
+		let clock = ManualClock::default();
+		let settings = Settings {
+			buffer_size: 3,
 			buffer_span_duration,
+			min_eval_size: 1,
+			error_threshold: 50.0,
 			..Settings::default()
-		});
+		};
+		for sticky_after_idle in [false, true] {
+			let count = Arc::new(AtomicUsize::new(0));
+			let mut cb = CircuitBreaker::new_with_clock(
+				Settings {
+					sticky_after_idle,
+					..settings
+				},
+				clock.clone(),
+			);
+			let counter = count.clone();
+			cb.on_idle_reset(move || {
+				counter.fetch_add(1, Ordering::SeqCst);
+			});
 
-		cb.advance_buffer_for_time(Instant::now());
-		assert_eq!(cb.get_buffer().get_cursor(), 0);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		std::thread::sleep(buffer_span_duration);
-		cb.advance_buffer_for_time(Instant::now());
-		assert_eq!(cb.get_buffer().get_cursor(), 1);
-		assert_eq!(cb.buffer.get_node_info(0).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(1).success_count, 0);
-		assert_eq!(cb.buffer.get_node_info(2).success_count, 0);
-		assert_eq!(cb.buffer.get_node_info(3).success_count, 0);
-		assert_eq!(cb.buffer.get_node_info(4).success_count, 0);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		std::thread::sleep(buffer_span_duration);
-		cb.advance_buffer_for_time(Instant::now());
-		assert_eq!(cb.get_buffer().get_cursor(), 2);
-		assert_eq!(cb.buffer.get_node_info(0).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(1).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(2).success_count, 0);
-		assert_eq!(cb.buffer.get_node_info(3).success_count, 0);
-		assert_eq!(cb.buffer.get_node_info(4).success_count, 0);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		std::thread::sleep(buffer_span_duration);
-		cb.advance_buffer_for_time(Instant::now());
-		assert_eq!(cb.get_buffer().get_cursor(), 3);
-		assert_eq!(cb.buffer.get_node_info(0).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(1).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(2).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(3).success_count, 0);
-		assert_eq!(cb.buffer.get_node_info(4).success_count, 0);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		std::thread::sleep(buffer_span_duration);
-		cb.advance_buffer_for_time(Instant::now());
-		assert_eq!(cb.get_buffer().get_cursor(), 4);
-		assert_eq!(cb.buffer.get_node_info(0).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(1).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(2).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(3).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(4).success_count, 0);
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		std::thread::sleep(buffer_span_duration);
-		cb.advance_buffer_for_time(Instant::now());
-		assert_eq!(cb.get_buffer().get_cursor(), 0);
-		assert_eq!(cb.buffer.get_node_info(0).success_count, 0);
-		assert_eq!(cb.buffer.get_node_info(1).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(2).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(3).success_count, 3);
-		assert_eq!(cb.buffer.get_node_info(4).success_count, 3);
-		cb.record::<(), &str>(Ok(()));
+			// an empty buffer being cleared isn't worth reporting
+			clock.advance(buffer_span_duration * 3);
+			assert_eq!(cb.get_state(), State::Closed);
+			assert_eq!(count.load(Ordering::SeqCst), 0);
 
-		// We skip 3 nodes ahead
-		std::thread::sleep(buffer_span_duration + buffer_span_duration + buffer_span_duration);
-		cb.evaluate_state();
+			cb.get_buffer().add_failures(3);
+			cb.get_buffer().add_success();
+			cb.get_buffer().advance(1);
+			assert_eq!(cb.get_error_rate(), 75.0);
+			cb.last_record = clock.now();
 
-		assert_eq!(cb.buffer.get_node_info(0).success_count, 1);
-		assert_eq!(cb.buffer.get_node_info(1).success_count, 0); // skipped
-		assert_eq!(cb.buffer.get_node_info(2).success_count, 0); // skipped
-		assert_eq!(cb.buffer.get_node_info(3).success_count, 0); // current
-		assert_eq!(cb.buffer.get_node_info(4).success_count, 3);
-		cb.advance_buffer_for_time(Instant::now());
-		assert_eq!(cb.get_buffer().get_cursor(), 3);
+			clock.advance(buffer_span_duration * 10);
+			cb.evaluate_state();
+			assert_eq!(count.load(Ordering::SeqCst), 1);
+			assert_eq!(cb.get_error_rate(), 0.0);
+			assert_eq!(matches!(cb.peek_state(), State::Open(_)), sticky_after_idle);
+		}
+	}
+
+	#[test]
+	fn pause_test() {
+		let clock = ManualClock::default();
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				buffer_size: 3,
+				buffer_span_duration,
+				min_eval_size: 1,
+				error_threshold: 60.0,
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		cb.record::<(), ()>(Err(()));
+		cb.record::<(), ()>(Ok(()));
+		clock.advance(buffer_span_duration);
+
+		cb.pause();
+		assert!(cb.is_paused());
+		clock.advance(Duration::from_secs(3600));
+		cb.record::<(), ()>(Err(()));
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.get_error_rate(), 50.0);
+		assert_eq!(cb.lifetime_totals(), (1, 1));
+
+		cb.resume();
+		assert!(!cb.is_paused());
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+		assert_eq!(cb.get_error_rate(), 50.0);
+		assert_eq!(cb.state_durations().closed, buffer_span_duration);
 	}
 
 	#[test]
-	fn evaluate_state_test() {
-		// Open state within the retry_timeout time
-		let retry_timeout = Duration::from_secs(1);
-		let mut cb = CircuitBreaker {
-			buffer: RingBuffer::new(5),
-			state: State::Open(Instant::now()),
-			last_record: Instant::now(),
-			start_time: Instant::now(),
-			trial_success: 0,
-			settings: Settings {
+	fn pause_retry_timeout_test() {
+		let clock = ManualClock::default();
+		let retry_timeout = Duration::from_secs(10);
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
 				retry_timeout,
 				..Settings::default()
 			},
-		};
-		cb.evaluate_state();
+			clock.clone(),
+		);
+		cb.transition_to(State::Open(clock.now()), clock.now());
+		clock.advance(Duration::from_secs(4));
+
+		cb.pause();
+		clock.advance(Duration::from_secs(60));
 		assert!(matches!(cb.get_state(), State::Open(_)));
+		cb.resume();
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.time_until_retry(), Some(Duration::from_secs(6)));
 
-		// Open state after the retry_timeout time
-		let retry_timeout = Duration::from_secs(1);
-		let mut cb = CircuitBreaker {
-			buffer: RingBuffer::new(5),
-			state: State::Open(Instant::now() - retry_timeout),
-			last_record: Instant::now(),
-			start_time: Instant::now(),
-			trial_success: 0,
-			settings: Settings {
-				retry_timeout,
-				..Settings::default()
-			},
-		};
-		cb.evaluate_state();
+		clock.advance(Duration::from_secs(6));
 		assert_eq!(cb.get_state(), State::HalfOpen);
+	}
 
-		// Closed state within the margin of error
-		let buffer_span_duration = Duration::from_secs(1);
-		let mut cb = CircuitBreaker {
-			buffer: RingBuffer::new(5),
-			state: State::Closed,
-			last_record: Instant::now(),
-			start_time: Instant::now(),
-			trial_success: 0,
-			settings: Settings {
-				min_eval_size: 4,
-				error_threshold: 39.99999,
-				buffer_span_duration,
-				..Settings::default()
-			},
-		};
-		cb.record::<(), &str>(Err(""));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
-		assert_eq!(cb.get_error_rate(), 20.0);
-		cb.evaluate_state();
+	#[test]
+	fn approx_p95_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 20,
+			p95_slow_threshold: Some(Duration::from_millis(150)),
+			..Settings::default()
+		});
+		assert_eq!(cb.approx_p95(), None);
+
+		// 19 of 20 fast calls keep the p95 in the first bucket
+		for _ in 0..19 {
+			cb.record_timed::<(), ()>(Ok(()), Duration::from_millis(20));
+		}
+		cb.record_timed::<(), ()>(Ok(()), Duration::from_secs(3));
+		assert_eq!(cb.approx_p95(), Some(Duration::from_millis(50)));
 		assert_eq!(cb.get_state(), State::Closed);
 
-		// Closed state an error larger than error_threshold
-		let buffer_span_duration = Duration::from_secs(1);
-		let mut cb = CircuitBreaker {
-			buffer: RingBuffer::new(5),
-			state: State::Closed,
-			last_record: Instant::now(),
-			start_time: Instant::now(),
-			trial_success: 0,
-			settings: Settings {
-				min_eval_size: 4,
-				error_threshold: 39.99999,
-				buffer_span_duration,
+		// one more slow call pushes the p95 into the 200ms bucket
+		cb.record_timed::<(), ()>(Ok(()), Duration::from_millis(120));
+		assert_eq!(cb.latency_distribution(), [19, 1, 0, 1]);
+		assert_eq!(cb.approx_p95(), Some(Duration::from_millis(200)));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.get_open_reason(), Some(OpenReason::Trip(TripKind::SlowCalls)));
+
+		let mut cb = CircuitBreaker::default();
+		cb.record_timed::<(), ()>(Ok(()), Duration::from_secs(1));
+		assert_eq!(cb.approx_p95(), Some(Duration::MAX));
+	}
+
+	#[test]
+	fn latency_distribution_test() {
+		let mut cb = CircuitBreaker::default();
+		cb.record_timed::<(), ()>(Ok(()), Duration::from_millis(10));
+		cb.record_timed::<(), ()>(Ok(()), Duration::from_millis(120));
+		cb.record_timed::<(), ()>(Err(()), Duration::from_millis(180));
+		cb.record_timed::<(), ()>(Err(()), Duration::from_millis(700));
+		cb.record_timed::<(), ()>(Err(()), Duration::from_secs(5));
+		cb.record::<(), ()>(Ok(()));
+		assert_eq!(cb.latency_distribution(), [1, 2, 1, 1]);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 3);
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 3);
+	}
+
+	#[test]
+	fn record_bool_routing_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				include_current_span: true,
+				trial_success_required: 2,
 				..Settings::default()
 			},
+			clock.clone(),
+		);
+		cb.record_bool(false);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+
+		// ignored while open
+		cb.record_bool(true);
+		assert_eq!(cb.lifetime_totals(), (0, 1));
+
+		// a half-open failure opens the circuit again
+		clock.advance(cb.get_settings().retry_timeout);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.record_bool(false);
+		assert!(matches!(cb.peek_state(), State::Open(_)));
+
+		// half-open successes count as trials
+		clock.advance(cb.get_settings().retry_timeout);
+		assert_eq!(cb.get_state(), State::HalfOpen);
+		cb.record_bool(true);
+		assert_eq!(cb.peek_state(), State::HalfOpen);
+		cb.record_bool(true);
+		assert_eq!(cb.peek_state(), State::Closed);
+		assert_eq!(cb.lifetime_totals(), (2, 2));
+	}
+
+	#[test]
+	fn record_with_weight_test() {
+		let settings = Settings {
+			min_eval_size: 4,
+			error_threshold: 30.0,
+			include_current_span: true,
+			..Settings::default()
 		};
-		cb.record::<(), &str>(Err(""));
-		cb.record::<(), &str>(Err(""));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.record::<(), &str>(Ok(()));
-		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+
+		let mut cb = CircuitBreaker::new(settings);
+		cb.record_with_weight(true, 3);
+		cb.record_with_weight(false, 1);
+		assert_eq!(cb.get_error_rate(), 25.0);
+		assert_eq!(cb.get_state(), State::Closed);
+
+		let mut cb = CircuitBreaker::new(settings);
+		cb.record_with_weight(true, 3);
+		cb.record_with_weight(false, 2);
 		assert_eq!(cb.get_error_rate(), 40.0);
-		cb.evaluate_state();
 		assert!(matches!(cb.get_state(), State::Open(_)));
 
-		// HalfOpen state with slowly increasing trial_success
-		let mut cb = CircuitBreaker {
-			buffer: RingBuffer::new(5),
-			state: State::HalfOpen,
-			last_record: Instant::now(),
-			start_time: Instant::now(),
-			trial_success: 0,
-			settings: Settings {
-				trial_success_required: 5,
+		// a weighted failure is still a single consecutive failure
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 100,
+			trip_on_consecutive: Some(2),
+			..settings
+		});
+		cb.record_with_weight(false, 5);
+		cb.record_with_weight(true, 0);
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.lifetime_totals(), (0, 1));
+		cb.record_with_weight(false, 1);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 6);
+	}
+
+	#[test]
+	fn record_with_weight_half_open_test() {
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 1,
+				include_current_span: true,
+				trial_success_required: 3,
 				..Settings::default()
 			},
-		};
-		cb.evaluate_state();
+			clock.clone(),
+		);
+		cb.record_bool(false);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		clock.advance(cb.get_settings().retry_timeout);
 		assert_eq!(cb.get_state(), State::HalfOpen);
-		cb.trial_success = 4;
-		cb.evaluate_state();
+
+		cb.record_with_weight(true, 10);
 		assert_eq!(cb.get_state(), State::HalfOpen);
-		cb.trial_success += 1;
-		cb.evaluate_state();
+		assert_eq!(cb.lifetime_totals(), (1, 1));
+	}
+
+	#[test]
+	fn record_err_if_test() {
+		let mut cb = CircuitBreaker::default();
+		let is_server_error = |status: &u16| *status >= 500;
+		cb.record_err_if::<(), u16, _>(Ok(()), is_server_error);
+		cb.record_err_if::<(), u16, _>(Err(404), is_server_error);
+		cb.record_err_if::<(), u16, _>(Err(503), is_server_error);
+		cb.record_err_if::<(), u16, _>(Err(400), is_server_error);
+		assert_eq!(
+			cb.get_buffer().get_node_info(0),
+			NodeInfo {
+				failure_count: 1,
+				success_count: 1,
+			}
+		);
+		assert_eq!(cb.lifetime_totals(), (1, 1));
+	}
+
+	#[test]
+	fn record_outcome_test() {
+		let mut cb = CircuitBreaker::default();
+		cb.record_outcome(Outcome::Success);
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 1);
+		cb.record_outcome(Outcome::Failure);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+		cb.record_outcome(Outcome::Timeout);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 2);
+		assert_eq!(cb.get_timeout_count(), 1);
+
+		let before = cb.debug_full();
+		cb.record_outcome(Outcome::Ignore);
+		assert_eq!(cb.debug_full(), before);
+		assert_eq!(cb.lifetime_totals(), (1, 2));
+	}
+
+	#[test]
+	fn record_timeout_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			buffer_span_duration,
+			error_threshold: 80.0,
+			..Settings::default()
+		});
+		cb.record_timeout();
+		cb.record::<(), ()>(Err(()));
+		cb.record::<(), ()>(Ok(()));
+		cb.record_timeout();
+		assert_eq!(cb.get_timeout_count(), 2);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 3);
+
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		assert_eq!(cb.get_error_rate(), 75.0);
 		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.get_timeout_count(), 2);
 	}
 
 	#[test]
@@ -744,6 +5006,18 @@ mod test {
 		assert!(std::ptr::eq(cb.get_buffer(), &cb.buffer));
 	}
 
+	#[test]
+	fn clone_buffer_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		cb.record::<(), &str>(Ok(()));
+		let mut buffer = cb.clone_buffer();
+		assert_eq!(buffer, cb.buffer);
+
+		buffer.add_failure();
+		assert_eq!(buffer.get_node_info(0).failure_count, 1);
+		assert_eq!(cb.buffer.get_node_info(0).failure_count, 0);
+	}
+
 	#[test]
 	fn get_trial_success_test() {
 		let mut cb = CircuitBreaker::new(Settings::default());
@@ -764,19 +5038,186 @@ mod test {
 			buffer_size: 666,
 			min_eval_size: 42,
 			error_threshold: 5.5,
+			threshold_mode: ThresholdMode::RelativeToBaseline {
+				baseline: 2.0,
+				margin: 1.0,
+			},
 			retry_timeout: Duration::from_millis(55),
 			buffer_span_duration: Duration::from_secs(80),
 			trial_success_required: 100,
+			window_mode: WindowMode::RollingLog(10),
+			transition_log_size: 3,
+			shadow: true,
+			close_cooldown: Duration::from_secs(3),
+			recover_on_error_rate: Some(5.0),
+			include_current_span: true,
+			trip_on_consecutive: Some(4),
+			inclusive_threshold: true,
+			trip_priority: [TripKind::ConsecutiveFailures, TripKind::ErrorRate, TripKind::SlowCalls],
+			sticky_after_idle: true,
+			open_policy: OpenPolicy::FailOpen,
+			half_open_buffer_size: Some(8),
+			denominator_policy: DenominatorPolicy::LastN(2),
+			p95_slow_threshold: Some(Duration::from_millis(200)),
+			eval_debounce: Some(Duration::from_millis(5)),
+			post_recovery_sensitive: Duration::from_secs(2),
+			trial_decay_interval: Some(Duration::from_secs(15)),
+			max_buffer_size: 4096,
+			evaluate_cursor_when_window_empty: true,
 		};
 		let cb = CircuitBreaker::new(settings);
 		assert_eq!(*cb.get_settings(), settings);
 	}
 
+	#[test]
+	fn rolling_log_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 4,
+			error_threshold: 50.0,
+			window_mode: WindowMode::RollingLog(4),
+			..Settings::default()
+		});
+
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Ok(()));
+		// no need to wait for the span to end as the current events are evaluated
+		assert_eq!(cb.get_error_rate(), 50.0);
+		assert_eq!(cb.get_state(), State::Closed);
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 0);
+
+		// the oldest failures are evicted at capacity
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(cb.get_error_rate(), 25.0);
+
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Err(""));
+		cb.record::<(), &str>(Err(""));
+		assert!(matches!(cb.get_state(), State::Open(_)));
+	}
+
+	#[test]
+	fn span_events_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			buffer_span_duration: Duration::ZERO,
+			min_eval_size: 4,
+			error_threshold: 50.0,
+			window_mode: WindowMode::SpanEvents(2),
+			..Settings::default()
+		});
+
+		cb.record::<(), &str>(Ok(()));
+		cb.record::<(), &str>(Err(""));
+		assert_eq!(cb.get_buffer().get_cursor(), 0);
+		assert_eq!(cb.get_span_event_count(), 2);
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+		assert_eq!(cb.get_span_event_count(), 1);
+
+		// time has no influence on the spans
+		cb.advance_buffer_for_time(Instant::now() + Duration::from_secs(500));
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+
+		// batches are split across spans
+		cb.record_many_failures(5);
+		assert_eq!(cb.get_buffer().get_cursor(), 3);
+		assert_eq!(cb.get_buffer().get_node_info(1).failure_count, 1);
+		assert_eq!(cb.get_buffer().get_node_info(2).failure_count, 2);
+		assert_eq!(cb.get_buffer().get_node_info(3).failure_count, 2);
+		assert_eq!(cb.get_error_rate(), 66.67);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+	}
+
 	#[test]
 	fn get_error_rate_test() {
 		// TODO
 	}
 
+	#[test]
+	fn can_ever_trip_test() {
+		assert!(CircuitBreaker::new(Settings::default()).can_ever_trip());
+		assert!(CircuitBreaker::new(Settings {
+			min_eval_size: 100,
+			window_mode: WindowMode::RollingLog(100),
+			..Settings::default()
+		})
+		.can_ever_trip());
+		assert!(!CircuitBreaker::new(Settings {
+			min_eval_size: 100,
+			window_mode: WindowMode::RollingLog(99),
+			..Settings::default()
+		})
+		.can_ever_trip());
+		assert!(!CircuitBreaker::new(Settings {
+			error_threshold: 100.0,
+			..Settings::default()
+		})
+		.can_ever_trip());
+		assert!(!CircuitBreaker::new(Settings {
+			threshold_mode: ThresholdMode::RelativeToBaseline {
+				baseline: 90.0,
+				margin: 20.0,
+			},
+			..Settings::default()
+		})
+		.can_ever_trip());
+	}
+
+	#[test]
+	fn since_last_record_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		let before = Instant::now();
+		cb.record::<(), &str>(Ok(()));
+		let after = Instant::now();
+
+		let since = cb.since_last_record(after + Duration::from_secs(5));
+		assert!(since >= Duration::from_secs(5));
+		assert!(since <= Duration::from_secs(5) + (after - before));
+		assert_eq!(cb.since_last_record(before), Duration::ZERO);
+
+		// nothing is recorded while open
+		cb.state = State::Open(Instant::now());
+		cb.last_event = before;
+		cb.record::<(), &str>(Ok(()));
+		assert_eq!(cb.since_last_record(after), after - before);
+	}
+
+	#[test]
+	fn summary_json_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			..Settings::default()
+		});
+		assert_eq!(cb.summary_json(), r#"{"state":"closed","error_rate":0,"cursor":0,"buffer_size":5,"trial_success":0}"#);
+
+		cb.record_many_failures(1);
+		cb.record_many_successes(7);
+		cb.buffer.advance(2);
+		cb.state = State::HalfOpen;
+		cb.trial_success = 3;
+		assert_eq!(
+			cb.summary_json(),
+			r#"{"state":"half_open","error_rate":12.5,"cursor":2,"buffer_size":5,"trial_success":3}"#
+		);
+
+		cb.state = State::Open(Instant::now());
+		assert!(cb.summary_json().starts_with(r#"{"state":"open","#));
+	}
+
+	#[test]
+	fn with_name_test() {
+		let cb = CircuitBreaker::default().with_name(String::from("payments"));
+		assert_eq!(cb.name(), "payments");
+		assert!(cb.summary_json().starts_with(r#"{"name":"payments","state":"closed","#));
+		assert!(format!("{cb:?}").starts_with(r#"CircuitBreaker { name: "payments", state: Closed"#));
+		assert!(cb.debug_full().contains(r#"name: "payments""#));
+
+		let cb = CircuitBreaker::default().with_name(String::from("say \"hi\"\n"));
+		assert!(cb.summary_json().starts_with(r#"{"name":"say \"hi\"\u000a","#));
+		assert_eq!(CircuitBreaker::default().name(), "");
+	}
+
 	#[test]
 	fn get_elapsed_time_test() {
 		let timeout = Instant::now();
@@ -792,6 +5233,49 @@ mod test {
 		assert_eq!(cb.get_elapsed_time(Duration::from_secs(5), timeout + Duration::from_secs(6)), Duration::from_secs(1));
 	}
 
+	#[test]
+	fn debug_timing_test() {
+		let start = Instant::now();
+		let cb = CircuitBreaker {
+			last_record: start,
+			settings: Settings {
+				buffer_span_duration: Duration::from_secs(1),
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+
+		assert_eq!(
+			cb.debug_timing(start + Duration::from_millis(2300)),
+			TimingInfo {
+				last_record_age: Duration::from_millis(2300),
+				span_elapsed: Duration::from_millis(300),
+				spans_since_last_record: 2,
+			}
+		);
+		assert_eq!(cb.debug_timing(start).spans_since_last_record, 0);
+	}
+
+	#[test]
+	fn time_budget_remaining_test() {
+		let start = Instant::now();
+		let cb = CircuitBreaker {
+			start_time: start,
+			last_record: start,
+			settings: Settings {
+				buffer_span_duration: Duration::from_secs(5),
+				..Settings::default()
+			},
+			..CircuitBreaker::default()
+		};
+
+		assert_eq!(cb.time_budget_remaining(start), Duration::from_secs(5));
+		assert_eq!(cb.time_budget_remaining(start + Duration::from_millis(2500)), Duration::from_millis(2500));
+		assert_eq!(cb.time_budget_remaining(start + Duration::from_millis(4999)), Duration::from_millis(1));
+		// the next span starts right at the end of the current one
+		assert_eq!(cb.time_budget_remaining(start + Duration::from_secs(5)), Duration::from_secs(5));
+	}
+
 	#[test]
 	fn end_2_end_test() {
 		let buffer_span_duration = Duration::from_millis(300);
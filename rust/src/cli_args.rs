@@ -1,9 +1,43 @@
-use std::time::Duration;
+use crate::{
+	circuit_breaker::{parse_duration, DenominatorPolicy, OpenPolicy, Settings, ThresholdMode, TripKind},
+	cli_helpers::exit_with_error,
+	error::SettingsError,
+	window::WindowMode,
+};
 
-use crate::{circuit_breaker::Settings, cli_helpers::exit_with_error};
+/// Get the command passed via `--watch` if any
+pub fn parse_watch(args: &[String]) -> Option<String> {
+	let index = args.iter().position(|arg| arg == "--watch")?;
+	match args.get(index.saturating_add(1)) {
+		Some(command) => Some(command.clone()),
+		None => exit_with_error("The watch flag requires an additional argument", 1),
+	}
+}
+
+/// Get the state passed via `--assert-final-state` if any, one of "closed",
+/// "open" or "halfopen"
+pub fn parse_assert_final_state(args: &[String]) -> Option<String> {
+	let index = args.iter().position(|arg| arg == "--assert-final-state")?;
+	match args.get(index.saturating_add(1)).map(String::as_str) {
+		Some(state @ ("closed" | "open" | "halfopen")) => Some(state.to_string()),
+		Some(_) => exit_with_error("The assert-final-state argument must be \"closed\", \"open\" or \"halfopen\"", 1),
+		None => exit_with_error("The assert-final-state flag requires an additional argument", 1),
+	}
+}
 
 pub fn parse_args(args: Vec<String>) -> Settings {
-	let mut settings: Settings = Default::default();
+	// a preset is applied first so explicit flags override it
+	let mut settings: Settings = match args.iter().position(|arg| arg == "--preset") {
+		None => Default::default(),
+		Some(index) => {
+			let name = args
+				.get(index.saturating_add(1))
+				.unwrap_or_else(|| exit_with_error("The preset flag requires an additional argument", 1));
+			Settings::preset(name).unwrap_or_else(|| {
+				exit_with_error(&format!("Unknown preset \"{name}\", use --list-presets to see all presets"), 1)
+			})
+		},
+	};
 
 	let mut args_iter = args.into_iter();
 	while let Some(arg) = args_iter.next() {
@@ -15,6 +49,13 @@ pub fn parse_args(args: Vec<String>) -> Settings {
 					.parse()
 					.unwrap_or_else(|_| exit_with_error("The buffer_size argument must be a number", 1));
 			},
+			"--max_buffer_size" => {
+				settings.max_buffer_size = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The max_buffer_size flag requires an additional argument", 1))
+					.parse()
+					.unwrap_or_else(|_| exit_with_error("The max_buffer_size argument must be a number", 1));
+			},
 			"-m" | "--min_eval_size" => {
 				settings.min_eval_size = args_iter
 					.next()
@@ -29,21 +70,59 @@ pub fn parse_args(args: Vec<String>) -> Settings {
 					.parse()
 					.unwrap_or_else(|_| exit_with_error("The error_threshold argument must be a number", 1));
 			},
+			"--preset" => {
+				// applied before all other flags
+				args_iter.next();
+			},
+			"--watch" => {
+				// parsed separately in parse_watch
+				args_iter.next();
+			},
+			"--assert-final-state" => {
+				// parsed separately in parse_assert_final_state
+				args_iter.next();
+			},
+			"--threshold_mode" => {
+				let mode = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The threshold_mode flag requires an additional argument", 1));
+				settings.threshold_mode = match mode.split(':').collect::<Vec<_>>().as_slice() {
+					["absolute"] => ThresholdMode::Absolute,
+					["relative", baseline, margin] => ThresholdMode::RelativeToBaseline {
+						baseline: baseline.parse().unwrap_or_else(|_| exit_with_error("The relative baseline must be a number", 1)),
+						margin: margin.parse().unwrap_or_else(|_| exit_with_error("The relative margin must be a number", 1)),
+					},
+					_ => {
+						exit_with_error("The threshold_mode argument must be \"absolute\" or \"relative:<BASELINE>:<MARGIN>\"", 1)
+					},
+				};
+			},
+			"--trip_priority" => {
+				let priority = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The trip_priority flag requires an additional argument", 1));
+				settings.trip_priority = parse_trip_priority(&priority).unwrap_or_else(|| {
+					exit_with_error(
+						"The trip_priority argument must be \"error_rate\", \"consecutive_failures\" and \"slow_calls\" comma separated without repeats",
+						1,
+					)
+				});
+			},
 			"-r" | "--retry_timeout" => {
 				let duration = args_iter
 					.next()
-					.unwrap_or_else(|| exit_with_error("The retry_timeout flag requires an additional argument", 1))
-					.parse()
-					.unwrap_or_else(|_| exit_with_error("The retry_timeout argument must be a number", 1));
-				settings.retry_timeout = Duration::from_secs(duration);
+					.unwrap_or_else(|| exit_with_error("The retry_timeout flag requires an additional argument", 1));
+				settings.retry_timeout = parse_duration(&duration).unwrap_or_else(|| {
+					exit_with_error("The retry_timeout argument must be a number of seconds like \"1.5\" or a duration", 1)
+				});
 			},
 			"-s" | "--buffer_span_duration" => {
 				let duration = args_iter
 					.next()
-					.unwrap_or_else(|| exit_with_error("The buffer_span_duration flag requires an additional argument", 1))
-					.parse()
-					.unwrap_or_else(|_| exit_with_error("The buffer_span_duration argument must be a number", 1));
-				settings.buffer_span_duration = Duration::from_secs(duration);
+					.unwrap_or_else(|| exit_with_error("The buffer_span_duration flag requires an additional argument", 1));
+				settings.buffer_span_duration = parse_duration(&duration).unwrap_or_else(|| {
+					exit_with_error("The buffer_span_duration argument must be a number of seconds like \"1.5\" or a duration", 1)
+				});
 			},
 			"-t" | "--trial_success_required" => {
 				settings.trial_success_required = args_iter
@@ -52,14 +131,190 @@ pub fn parse_args(args: Vec<String>) -> Settings {
 					.parse()
 					.unwrap_or_else(|_| exit_with_error("The trial_success_required argument must be a number", 1));
 			},
+			"--close_cooldown" => {
+				let duration = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The close_cooldown flag requires an additional argument", 1));
+				settings.close_cooldown = parse_duration(&duration).unwrap_or_else(|| {
+					exit_with_error("The close_cooldown argument must be a number of seconds like \"1.5\" or a duration", 1)
+				});
+			},
+			"--trial_decay_interval" => {
+				let interval = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The trial_decay_interval flag requires an additional argument", 1));
+				settings.trial_decay_interval = Some(parse_duration(&interval).unwrap_or_else(|| {
+					exit_with_error("The trial_decay_interval argument must be a duration like \"250ms\" or \"1s\"", 1)
+				}));
+			},
+			"--post_recovery_sensitive" => {
+				let duration = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The post_recovery_sensitive flag requires an additional argument", 1));
+				settings.post_recovery_sensitive = parse_duration(&duration).unwrap_or_else(|| {
+					exit_with_error(
+						"The post_recovery_sensitive argument must be a number of seconds like \"1.5\" or a duration",
+						1,
+					)
+				});
+			},
+			"--recover_on_error_rate" => {
+				let rate: f32 = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The recover_on_error_rate flag requires an additional argument", 1))
+					.parse()
+					.unwrap_or_else(|_| exit_with_error("The recover_on_error_rate argument must be a number", 1));
+				settings.recover_on_error_rate = Some(rate);
+			},
+			"--evaluate_cursor_when_window_empty" => {
+				settings.evaluate_cursor_when_window_empty = true;
+			},
+			"--include_current_span" => {
+				settings.include_current_span = true;
+			},
+			"--trip_on_consecutive" => {
+				let failures = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The trip_on_consecutive flag requires an additional argument", 1))
+					.parse()
+					.unwrap_or_else(|_| exit_with_error("The trip_on_consecutive argument must be a number", 1));
+				settings.trip_on_consecutive = Some(failures);
+			},
+			"--half_open_buffer_size" => {
+				let size = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The half_open_buffer_size flag requires an additional argument", 1))
+					.parse()
+					.unwrap_or_else(|_| exit_with_error("The half_open_buffer_size argument must be a number", 1));
+				settings.half_open_buffer_size = Some(size);
+			},
+			"--denominator_policy" => {
+				let policy = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The denominator_policy flag requires an additional argument", 1));
+				settings.denominator_policy = match policy.split_once(':') {
+					None if policy == "whole_window" => DenominatorPolicy::WholeWindow,
+					None if policy == "trailing_span" => DenominatorPolicy::TrailingSpan,
+					Some(("last_n", spans)) => match spans.parse() {
+						Ok(0) => exit_with_error(&SettingsError::ZeroLastNSpans.to_string(), 1),
+						Ok(spans) => DenominatorPolicy::LastN(spans),
+						Err(_) => exit_with_error("The last_n argument must be a number", 1),
+					},
+					_ => exit_with_error(
+						"The denominator_policy argument must be \"whole_window\", \"trailing_span\" or \"last_n:<N>\"",
+						1,
+					),
+				};
+			},
+			"--p95_slow_threshold" => {
+				let threshold = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The p95_slow_threshold flag requires an additional argument", 1));
+				settings.p95_slow_threshold = Some(parse_duration(&threshold).unwrap_or_else(|| {
+					exit_with_error("The p95_slow_threshold argument must be a duration like \"250ms\" or \"1s\"", 1)
+				}));
+			},
+			"--eval_debounce" => {
+				let debounce = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The eval_debounce flag requires an additional argument", 1));
+				settings.eval_debounce = Some(parse_duration(&debounce).unwrap_or_else(|| {
+					exit_with_error("The eval_debounce argument must be a duration like \"250ms\" or \"1s\"", 1)
+				}));
+			},
+			"--inclusive_threshold" => {
+				settings.inclusive_threshold = true;
+			},
+			"--sticky_after_idle" => {
+				settings.sticky_after_idle = true;
+			},
+			"--open_policy" => {
+				let policy = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The open_policy flag requires an additional argument", 1));
+				settings.open_policy = match policy.as_str() {
+					"fail_closed" => OpenPolicy::FailClosed,
+					"fail_open" => OpenPolicy::FailOpen,
+					_ => exit_with_error("The open_policy argument must be \"fail_closed\" or \"fail_open\"", 1),
+				};
+			},
+			"--shadow" => {
+				settings.shadow = true;
+			},
+			"--transition_log_size" => {
+				settings.transition_log_size = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The transition_log_size flag requires an additional argument", 1))
+					.parse()
+					.unwrap_or_else(|_| exit_with_error("The transition_log_size argument must be a number", 1));
+			},
+			"--span_events" => {
+				let span_events = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The span_events flag requires an additional argument", 1))
+					.parse()
+					.unwrap_or_else(|_| exit_with_error("The span_events argument must be a number", 1));
+				settings.window_mode = WindowMode::SpanEvents(span_events);
+			},
+			"-w" | "--window_mode" => {
+				let mode = args_iter
+					.next()
+					.unwrap_or_else(|| exit_with_error("The window_mode flag requires an additional argument", 1));
+				settings.window_mode = match mode.split_once(':') {
+					None if mode == "spans" => WindowMode::Spans,
+					Some(("span_events", span_events)) => WindowMode::SpanEvents(
+						span_events.parse().unwrap_or_else(|_| exit_with_error("The span_events argument must be a number", 1)),
+					),
+					Some(("rolling_log", capacity)) => WindowMode::RollingLog(
+						capacity.parse().unwrap_or_else(|_| exit_with_error("The rolling_log capacity must be a number", 1)),
+					),
+					_ => exit_with_error(
+						"The window_mode argument must be \"spans\", \"span_events:<N>\" or \"rolling_log:<CAPACITY>\"",
+						1,
+					),
+				};
+			},
 			_ => {},
 		}
 	}
+
+	if let Err(error) = settings.check_max_buffer_size() {
+		exit_with_error(&error.to_string(), 1);
+	}
+	if settings.buffer_span_duration.is_zero() && !matches!(settings.window_mode, WindowMode::SpanEvents(_)) {
+		exit_with_error("A buffer_span_duration of 0 requires the span_events flag to advance spans by event count", 1);
+	}
 	settings
 }
 
+/// Parse a comma separated `Settings.trip_priority` like
+/// "slow_calls,error_rate", kinds left out follow in their default order
+fn parse_trip_priority(input: &str) -> Option<[TripKind; 3]> {
+	let mut priority = Vec::with_capacity(TripKind::ALL.len());
+	for kind in input.split(',') {
+		let kind = match kind {
+			"error_rate" => TripKind::ErrorRate,
+			"consecutive_failures" => TripKind::ConsecutiveFailures,
+			"slow_calls" => TripKind::SlowCalls,
+			_ => return None,
+		};
+		if priority.contains(&kind) {
+			return None;
+		}
+		priority.push(kind);
+	}
+	for kind in TripKind::ALL {
+		if !priority.contains(&kind) {
+			priority.push(kind);
+		}
+	}
+	priority.try_into().ok()
+}
+
 #[cfg(test)]
 mod tests {
+	use std::time::Duration;
+
 	use super::*;
 
 	#[test]
@@ -87,6 +342,7 @@ mod tests {
 				retry_timeout: Duration::from_secs(200),
 				buffer_span_duration: Duration::from_secs(550),
 				trial_success_required: 666,
+				..Default::default()
 			}
 		);
 	}
@@ -116,6 +372,7 @@ mod tests {
 				retry_timeout: Duration::from_secs(62),
 				buffer_span_duration: Duration::from_secs(279),
 				trial_success_required: 0,
+				..Default::default()
 			}
 		);
 	}
@@ -268,6 +525,30 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn parse_args_retry_timeout_fractional() {
+		assert_eq!(
+			parse_args(vec![String::from("--retry_timeout"), String::from("1.5")]),
+			Settings {
+				retry_timeout: Duration::from_millis(1500),
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			parse_args(vec![String::from("-r"), String::from("0.25")]),
+			Settings {
+				retry_timeout: Duration::from_millis(250),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_retry_timeout_error_invalid() {
+		parse_args(vec![String::from("-r"), String::from("abc")]);
+	}
+
 	#[test]
 	#[should_panic]
 	fn parse_args_retry_timeout_error_negative() {
@@ -296,9 +577,15 @@ mod tests {
 			}
 		);
 		assert_eq!(
-			parse_args(vec![String::from("-s"), String::from("0")]),
+			parse_args(vec![
+				String::from("-s"),
+				String::from("0"),
+				String::from("--span_events"),
+				String::from("10")
+			]),
 			Settings {
 				buffer_span_duration: Duration::from_secs(0),
+				window_mode: WindowMode::SpanEvents(10),
 				..Default::default()
 			}
 		);
@@ -311,6 +598,36 @@ mod tests {
 		);
 	}
 
+	#[test]
+	#[should_panic]
+	fn parse_args_buffer_span_duration_error_zero() {
+		parse_args(vec![String::from("-s"), String::from("0")]);
+	}
+
+	#[test]
+	fn parse_args_span_events() {
+		assert_eq!(
+			parse_args(vec![String::from("--span_events"), String::from("10")]),
+			Settings {
+				window_mode: WindowMode::SpanEvents(10),
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			parse_args(vec![String::from("-w"), String::from("span_events:3")]),
+			Settings {
+				window_mode: WindowMode::SpanEvents(3),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_span_events_error_missing() {
+		parse_args(vec![String::from("--span_events")]);
+	}
+
 	#[test]
 	#[should_panic]
 	fn parse_args_buffer_span_duration_error_negative() {
@@ -371,4 +688,582 @@ mod tests {
 	fn parse_args_trial_success_required_error_missing2() {
 		parse_args(vec![String::from("-t"), String::from("-t")]);
 	}
+
+	#[test]
+	fn parse_args_window_mode() {
+		assert_eq!(
+			parse_args(vec![String::from("--window_mode"), String::from("spans")]),
+			Settings {
+				window_mode: WindowMode::Spans,
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			parse_args(vec![String::from("-w"), String::from("rolling_log:50")]),
+			Settings {
+				window_mode: WindowMode::RollingLog(50),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_window_mode_error_invalid() {
+		parse_args(vec![String::from("-w"), String::from("rolling")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_window_mode_error_capacity() {
+		parse_args(vec![String::from("-w"), String::from("rolling_log:abc")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_window_mode_error_missing() {
+		parse_args(vec![String::from("-w")]);
+	}
+
+	#[test]
+	fn parse_args_threshold_mode() {
+		assert_eq!(
+			parse_args(vec![String::from("--threshold_mode"), String::from("absolute")]),
+			Settings {
+				threshold_mode: ThresholdMode::Absolute,
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			parse_args(vec![String::from("--threshold_mode"), String::from("relative:1.5:2")]),
+			Settings {
+				threshold_mode: ThresholdMode::RelativeToBaseline {
+					baseline: 1.5,
+					margin: 2.0,
+				},
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_threshold_mode_error_invalid() {
+		parse_args(vec![String::from("--threshold_mode"), String::from("relative:1.5")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_threshold_mode_error_missing() {
+		parse_args(vec![String::from("--threshold_mode")]);
+	}
+
+	#[test]
+	fn parse_watch_test() {
+		assert_eq!(parse_watch(&[String::from("-b"), String::from("10")]), None);
+		assert_eq!(
+			parse_watch(&[
+				String::from("--watch"),
+				String::from("curl -sf localhost"),
+				String::from("-a")
+			]),
+			Some(String::from("curl -sf localhost"))
+		);
+		assert_eq!(
+			parse_args(vec![
+				String::from("--watch"),
+				String::from("-b"),
+				String::from("-t"),
+				String::from("5")
+			]),
+			Settings {
+				trial_success_required: 5,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_watch_error_missing() {
+		parse_watch(&[String::from("--watch")]);
+	}
+
+	#[test]
+	fn parse_assert_final_state_test() {
+		assert_eq!(parse_assert_final_state(&[String::from("--batch")]), None);
+		assert_eq!(
+			parse_assert_final_state(&[
+				String::from("--batch"),
+				String::from("--assert-final-state"),
+				String::from("halfopen")
+			]),
+			Some(String::from("halfopen"))
+		);
+		assert_eq!(
+			parse_args(vec![
+				String::from("--assert-final-state"),
+				String::from("open"),
+				String::from("-t"),
+				String::from("5")
+			]),
+			Settings {
+				trial_success_required: 5,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_assert_final_state_error_invalid() {
+		parse_assert_final_state(&[String::from("--assert-final-state"), String::from("half_open")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_assert_final_state_error_missing() {
+		parse_assert_final_state(&[String::from("--assert-final-state")]);
+	}
+
+	#[test]
+	fn parse_args_preset() {
+		assert_eq!(
+			parse_args(vec![String::from("--preset"), String::from("aggressive")]),
+			Settings::preset("aggressive").unwrap()
+		);
+		assert_eq!(
+			parse_args(vec![
+				String::from("-e"),
+				String::from("50"),
+				String::from("--preset"),
+				String::from("conservative"),
+				String::from("-b"),
+				String::from("3"),
+			]),
+			Settings {
+				error_threshold: 50.0,
+				buffer_size: 3,
+				..Settings::preset("conservative").unwrap()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_preset_error_unknown() {
+		parse_args(vec![String::from("--preset"), String::from("unknown")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_preset_error_missing() {
+		parse_args(vec![String::from("--preset")]);
+	}
+
+	#[test]
+	fn parse_args_transition_log_size() {
+		assert_eq!(
+			parse_args(vec![String::from("--transition_log_size"), String::from("0")]),
+			Settings {
+				transition_log_size: 0,
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			parse_args(vec![String::from("--transition_log_size"), String::from("99")]),
+			Settings {
+				transition_log_size: 99,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_transition_log_size_error_negative() {
+		parse_args(vec![String::from("--transition_log_size"), String::from("-1")]);
+	}
+
+	#[test]
+	fn parse_args_shadow() {
+		assert_eq!(
+			parse_args(vec![String::from("--shadow")]),
+			Settings {
+				shadow: true,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	fn parse_args_close_cooldown() {
+		assert_eq!(
+			parse_args(vec![String::from("--close_cooldown"), String::from("15")]),
+			Settings {
+				close_cooldown: Duration::from_secs(15),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_close_cooldown_error_negative() {
+		parse_args(vec![String::from("--close_cooldown"), String::from("-9")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_close_cooldown_error_missing() {
+		parse_args(vec![String::from("--close_cooldown")]);
+	}
+
+	#[test]
+	fn parse_args_recover_on_error_rate() {
+		assert_eq!(
+			parse_args(vec![String::from("--recover_on_error_rate"), String::from("12.5")]),
+			Settings {
+				recover_on_error_rate: Some(12.5),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_recover_on_error_rate_error_invalid() {
+		parse_args(vec![String::from("--recover_on_error_rate"), String::from("abc")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_recover_on_error_rate_error_missing() {
+		parse_args(vec![String::from("--recover_on_error_rate")]);
+	}
+
+	#[test]
+	fn parse_args_evaluate_cursor_when_window_empty() {
+		assert_eq!(
+			parse_args(vec![String::from("--evaluate_cursor_when_window_empty")]),
+			Settings {
+				evaluate_cursor_when_window_empty: true,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	fn parse_args_include_current_span() {
+		assert_eq!(
+			parse_args(vec![String::from("--include_current_span")]),
+			Settings {
+				include_current_span: true,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	fn parse_args_trip_on_consecutive() {
+		assert_eq!(
+			parse_args(vec![String::from("--trip_on_consecutive"), String::from("7")]),
+			Settings {
+				trip_on_consecutive: Some(7),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_trip_on_consecutive_error_negative() {
+		parse_args(vec![String::from("--trip_on_consecutive"), String::from("-7")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_trip_on_consecutive_error_missing() {
+		parse_args(vec![String::from("--trip_on_consecutive")]);
+	}
+
+	#[test]
+	fn parse_args_inclusive_threshold() {
+		assert_eq!(
+			parse_args(vec![String::from("--inclusive_threshold")]),
+			Settings {
+				inclusive_threshold: true,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	fn parse_args_trip_priority() {
+		assert_eq!(
+			parse_args(vec![
+				String::from("--trip_priority"),
+				String::from("consecutive_failures,error_rate")
+			]),
+			Settings {
+				trip_priority: [TripKind::ConsecutiveFailures, TripKind::ErrorRate, TripKind::SlowCalls],
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			parse_args(vec![String::from("--trip_priority"), String::from("slow_calls")]),
+			Settings {
+				trip_priority: [TripKind::SlowCalls, TripKind::ErrorRate, TripKind::ConsecutiveFailures],
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_trip_priority_error_invalid() {
+		parse_args(vec![String::from("--trip_priority"), String::from("error_rate,error_rate")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_trip_priority_error_missing() {
+		parse_args(vec![String::from("--trip_priority")]);
+	}
+
+	#[test]
+	fn parse_args_sticky_after_idle() {
+		assert_eq!(
+			parse_args(vec![String::from("--sticky_after_idle")]),
+			Settings {
+				sticky_after_idle: true,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	fn parse_args_open_policy() {
+		assert_eq!(
+			parse_args(vec![String::from("--open_policy"), String::from("fail_open")]),
+			Settings {
+				open_policy: OpenPolicy::FailOpen,
+				..Default::default()
+			}
+		);
+		assert_eq!(parse_args(vec![String::from("--open_policy"), String::from("fail_closed")]), Settings::default());
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_open_policy_error_invalid() {
+		parse_args(vec![String::from("--open_policy"), String::from("fail_sometimes")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_open_policy_error_missing() {
+		parse_args(vec![String::from("--open_policy")]);
+	}
+
+	#[test]
+	fn parse_args_half_open_buffer_size() {
+		assert_eq!(
+			parse_args(vec![String::from("--half_open_buffer_size"), String::from("6")]),
+			Settings {
+				half_open_buffer_size: Some(6),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_half_open_buffer_size_error_invalid() {
+		parse_args(vec![String::from("--half_open_buffer_size"), String::from("six")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_half_open_buffer_size_error_missing() {
+		parse_args(vec![String::from("--half_open_buffer_size")]);
+	}
+
+	#[test]
+	fn parse_args_denominator_policy() {
+		assert_eq!(
+			parse_args(vec![String::from("--denominator_policy"), String::from("trailing_span")]),
+			Settings {
+				denominator_policy: DenominatorPolicy::TrailingSpan,
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			parse_args(vec![String::from("--denominator_policy"), String::from("last_n:3")]),
+			Settings {
+				denominator_policy: DenominatorPolicy::LastN(3),
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			parse_args(vec![String::from("--denominator_policy"), String::from("whole_window")]),
+			Settings::default()
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_denominator_policy_error_invalid() {
+		parse_args(vec![String::from("--denominator_policy"), String::from("last_n:x")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_denominator_policy_error_zero() {
+		parse_args(vec![String::from("--denominator_policy"), String::from("last_n:0")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_denominator_policy_error_missing() {
+		parse_args(vec![String::from("--denominator_policy")]);
+	}
+
+	#[test]
+	fn parse_args_p95_slow_threshold() {
+		assert_eq!(
+			parse_args(vec![String::from("--p95_slow_threshold"), String::from("250ms")]),
+			Settings {
+				p95_slow_threshold: Some(Duration::from_millis(250)),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_p95_slow_threshold_error_invalid() {
+		parse_args(vec![String::from("--p95_slow_threshold"), String::from("slow")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_p95_slow_threshold_error_missing() {
+		parse_args(vec![String::from("--p95_slow_threshold")]);
+	}
+
+	#[test]
+	fn parse_args_post_recovery_sensitive() {
+		assert_eq!(
+			parse_args(vec![String::from("--post_recovery_sensitive"), String::from("2.5")]),
+			Settings {
+				post_recovery_sensitive: Duration::from_millis(2500),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_post_recovery_sensitive_error_invalid() {
+		parse_args(vec![String::from("--post_recovery_sensitive"), String::from("-9")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_post_recovery_sensitive_error_missing() {
+		parse_args(vec![String::from("--post_recovery_sensitive")]);
+	}
+
+	#[test]
+	fn parse_args_max_buffer_size() {
+		assert_eq!(
+			parse_args(vec![
+				String::from("--max_buffer_size"),
+				String::from("20000000"),
+				String::from("-b"),
+				String::from("10000000")
+			]),
+			Settings {
+				buffer_size: 10_000_000,
+				max_buffer_size: 20_000_000,
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_max_buffer_size_error_exceeded() {
+		parse_args(vec![String::from("--buffer_size"), String::from("100000000")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_max_buffer_size_error_half_open_exceeded() {
+		parse_args(vec![
+			String::from("--half_open_buffer_size"),
+			String::from("8"),
+			String::from("--max_buffer_size"),
+			String::from("4"),
+		]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_max_buffer_size_error_invalid() {
+		parse_args(vec![String::from("--max_buffer_size"), String::from("-9")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_max_buffer_size_error_missing() {
+		parse_args(vec![String::from("--max_buffer_size")]);
+	}
+
+	#[test]
+	fn parse_args_trial_decay_interval() {
+		assert_eq!(
+			parse_args(vec![String::from("--trial_decay_interval"), String::from("30s")]),
+			Settings {
+				trial_decay_interval: Some(Duration::from_secs(30)),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_trial_decay_interval_error_invalid() {
+		parse_args(vec![String::from("--trial_decay_interval"), String::from("later")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_trial_decay_interval_error_missing() {
+		parse_args(vec![String::from("--trial_decay_interval")]);
+	}
+
+	#[test]
+	fn parse_args_eval_debounce() {
+		assert_eq!(
+			parse_args(vec![String::from("--eval_debounce"), String::from("5ms")]),
+			Settings {
+				eval_debounce: Some(Duration::from_millis(5)),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_eval_debounce_error_invalid() {
+		parse_args(vec![String::from("--eval_debounce"), String::from("often")]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn parse_args_eval_debounce_error_missing() {
+		parse_args(vec![String::from("--eval_debounce")]);
+	}
 }
@@ -1,3 +1,13 @@
+use std::{
+	io::{self, Read},
+	process::{Command, Stdio},
+};
+
+use crate::{
+	circuit_breaker::{CircuitBreaker, Settings, State},
+	clock::ManualClock,
+};
+
 pub fn exit_with_error(error: &str, code: i32) -> ! {
 	eprintln!("{error}");
 
@@ -8,24 +18,186 @@ pub fn exit_with_error(error: &str, code: i32) -> ! {
 	}
 }
 
+/// Run `command` through the shell, treating a zero exit code as success and
+/// anything else, including a command that can't be found, as failure
+pub fn run_command(command: &str) -> Result<(), String> {
+	match Command::new("sh").arg("-c").arg(command).stdout(Stdio::null()).stderr(Stdio::null()).status() {
+		Ok(status) if status.success() => Ok(()),
+		Ok(status) => Err(format!("Command exited with {status}")),
+		Err(error) => Err(format!("Command could not be run: {error}")),
+	}
+}
+
+/// Record every "s" read from `input` as a success and every "f" as a failure,
+/// the same keys as in the visualizer, and advance `clock`, the clock of `cb`,
+/// by one span for every ".", ignoring everything else. Without a "." all
+/// events land in the current span, which only counts with
+/// `Settings.include_current_span`.
+pub fn run_batch(cb: &mut CircuitBreaker, clock: &ManualClock, mut input: impl Read) -> io::Result<()> {
+	let mut events = Vec::new();
+	input.read_to_end(&mut events)?;
+	for event in events {
+		match event {
+			b's' => cb.record_bool(true),
+			b'f' => cb.record_bool(false),
+			b'.' => clock.advance(cb.get_settings().buffer_span_duration),
+			_ => {},
+		}
+	}
+	Ok(())
+}
+
+/// Check that the state of `cb` is `expected`, one of "closed", "open" or
+/// "halfopen"
+pub fn assert_final_state(cb: &mut CircuitBreaker, expected: &str) -> Result<(), String> {
+	let actual = match cb.get_state() {
+		State::Closed => "closed",
+		State::Open(_) => "open",
+		State::HalfOpen => "halfopen",
+	};
+	if actual == expected {
+		Ok(())
+	} else {
+		Err(format!("Expected the final state to be {expected} but it is {actual}"))
+	}
+}
+
+/// Check if `columns`, the value of the `COLUMNS` environment variable, is too
+/// narrow for the full visualizer
+pub fn is_narrow_terminal(columns: Option<String>) -> bool {
+	columns.and_then(|columns| columns.trim().parse::<usize>().ok()).is_some_and(|columns| columns <= 80)
+}
+
+pub fn list_presets() -> String {
+	let mut output = String::new();
+	for name in Settings::PRESETS {
+		if let Some(settings) = Settings::preset(name) {
+			output.push_str(&format!(
+				"{name}:\n  buffer_size={} buffer_span_duration={}s min_eval_size={} error_threshold={} retry_timeout={}s trial_success_required={}\n",
+				settings.buffer_size,
+				settings.buffer_span_duration.as_secs(),
+				settings.min_eval_size,
+				settings.error_threshold,
+				settings.retry_timeout.as_secs(),
+				settings.trial_success_required
+			));
+		}
+	}
+	output
+}
+
+pub fn stats_summary(cb: &CircuitBreaker) -> String {
+	let (successes, failures) = cb.lifetime_totals();
+	format!(
+		"Successes: {successes}\nFailures: {failures}\nTrips: {}\nTime open: {}s\n",
+		cb.state_entry_counts().open,
+		cb.state_durations().open.as_secs()
+	)
+}
+
 pub fn help() -> String {
 	r#"
 Usage: circuitbreaker [OPTIONS]
 
 Options:
   -b, --buffer_size            SIZE    Specify the capacity of the ring buffer.
+      --max_buffer_size        SIZE    Reject a larger buffer_size,
+                                       half_open_buffer_size or rolling_log
+                                       CAPACITY, defaults to 1000000.
   -m, --min_eval_size          NUMBER  Define the minimum number of events
                                        required in the buffer to evaluate the
                                        error rate.
   -e, --error_threshold        FLOAT   Set the error rate percentage that will
                                        trigger the circuit to open.
+      --inclusive_threshold            Also trip when the error rate equals the
+                                       threshold.
+      --threshold_mode         MODE    Trip on the "absolute" error_threshold
+                                       or "relative:<BASELINE>:<MARGIN>" when
+                                       the error rate exceeds BASELINE+MARGIN.
+      --trip_on_consecutive    NUMBER  Open the circuit right away after NUMBER
+                                       consecutive failures.
+      --trip_priority          KINDS   The order "error_rate",
+                                       "consecutive_failures" and "slow_calls"
+                                       are checked in, comma separated. Kinds
+                                       left out are checked last.
+      --p95_slow_threshold     DURATION
+                                       Open the circuit when the approximate
+                                       p95 latency exceeds DURATION, e.g.
+                                       "250ms".
   -r, --retry_timeout          SECONDS Specify the duration (in seconds) the
                                        circuit breaker remains open before
-                                       transitioning to half-open.
+                                       transitioning to half-open. 0 probes
+                                       right away.
+      --close_cooldown         SECONDS Specify the duration (in seconds) after
+                                       closing a half-open circuit during which
+                                       it won't open again.
+      --post_recovery_sensitive
+                               SECONDS Open a circuit again on the first
+                                       failure within SECONDS after closing a
+                                       half-open circuit.
   -s, --buffer_span_duration   SECONDS Determine the duration (in seconds) each
-                                       node/span in the buffer stores data.
+                                       node/span in the buffer stores data. A
+                                       duration of 0 requires --span_events.
+      --span_events            NUMBER  Advance to the next node/span after
+                                       NUMBER events instead of by time.
   -t, --trial_success_required NUMBER  Set the number of consecutive successes
                                        required to close a half-open circuit.
+      --trial_decay_interval   DURATION
+                                       Take back one trial success per
+                                       DURATION without a new success, e.g.
+                                       "30s".
+      --recover_on_error_rate  FLOAT   Close a half-open circuit once a sample
+                                       of trial_success_required events has an
+                                       error rate below FLOAT instead.
+      --half_open_buffer_size  NUMBER  Record half-open probes into a buffer of
+                                       NUMBER probes and close once its error
+                                       rate doesn't trip.
+  -w, --window_mode            MODE    Record into time-based "spans", spans
+                                       of "span_events:<N>" events or a
+                                       "rolling_log:<CAPACITY>" of the last
+                                       CAPACITY events.
+      --denominator_policy     POLICY  Calculate the error rate from the
+                                       "whole_window", the "trailing_span" or
+                                       the "last_n:<N>" completed spans.
+      --include_current_span           Include the current, still filling
+                                       node/span in the error rate.
+      --evaluate_cursor_when_window_empty
+                                       Calculate the error rate from the
+                                       current node/span while all others are
+                                       empty.
+      --sticky_after_idle              Keep the error rate from before a long
+                                       idle gap cleared the buffer for one more
+                                       evaluation.
+      --eval_debounce          DURATION
+                                       Evaluate the state at most once per
+                                       DURATION between recordings, e.g.
+                                       "5ms".
+      --transition_log_size    NUMBER  Keep the last NUMBER state transitions.
+      --open_policy            POLICY  Reject calls while open with
+                                       "fail_closed" or permit them without
+                                       recording with "fail_open".
+      --shadow                         Evaluate the state but always permit
+                                       calls.
+      --watch                  COMMAND Run COMMAND every second and record its
+                                       exit code as success (0) or failure.
+      --preset                 NAME    Start from the named preset settings and
+                                       apply all other flags on top.
+      --list-presets                   List all presets and their settings and
+                                       exit.
+      --stats-on-exit                  Print the successes, failures, trips and
+                                       time spent open on exit.
+      --batch                          Record "s" as success and "f" as failure
+                                       from stdin without the visualizer and
+                                       print the final state. Each "." moves on
+                                       to the next span, events of the current
+                                       span only count with
+                                       --include_current_span.
+      --assert-final-state     STATE   Exit with an error unless the final
+                                       state of --batch is "closed", "open" or
+                                       "halfopen".
+      --minimal                        Render one line per node/span, e.g. for
+                                       narrow terminals. Used automatically
+                                       when COLUMNS is 80 or less.
   -a, --noautoplay                     Don't auto-play the visualizer and
                                        refresh every second.
   -h, --help                           Display this help message and exit.
@@ -37,7 +209,75 @@ Options:
 #[cfg(test)]
 mod test {
 	use super::*;
-	use crate::circuit_breaker::Settings;
+
+	#[test]
+	fn run_command_test() {
+		assert_eq!(run_command("true"), Ok(()));
+		assert!(run_command("false").is_err());
+		assert!(run_command("exit 3").is_err());
+		assert!(run_command("this_command_does_not_exist_42").is_err());
+	}
+
+	#[test]
+	fn batch_assert_final_state_test() {
+		let settings = Settings {
+			trip_on_consecutive: Some(3),
+			..Settings::default()
+		};
+
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(settings, clock.clone());
+		assert!(run_batch(&mut cb, &clock, io::Cursor::new(b"s f\nf f\n".to_vec())).is_ok());
+		assert_eq!(cb.lifetime_totals(), (1, 3));
+		assert_eq!(assert_final_state(&mut cb, "open"), Ok(()));
+		assert_eq!(
+			assert_final_state(&mut cb, "closed"),
+			Err(String::from("Expected the final state to be closed but it is open"))
+		);
+
+		let mut cb = CircuitBreaker::new_with_clock(settings, clock.clone());
+		assert!(run_batch(&mut cb, &clock, io::Cursor::new(b"ssfs".to_vec())).is_ok());
+		assert_eq!(assert_final_state(&mut cb, "closed"), Ok(()));
+		assert!(assert_final_state(&mut cb, "halfopen").is_err());
+	}
+
+	#[test]
+	fn batch_span_ticks_test() {
+		let settings = Settings {
+			min_eval_size: 2,
+			..Settings::default()
+		};
+
+		// the events stay in the current span, which isn't evaluated
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(settings, clock.clone());
+		assert!(run_batch(&mut cb, &clock, io::Cursor::new(b"fff".to_vec())).is_ok());
+		assert_eq!(assert_final_state(&mut cb, "closed"), Ok(()));
+
+		let clock = ManualClock::default();
+		let mut cb = CircuitBreaker::new_with_clock(settings, clock.clone());
+		assert!(run_batch(&mut cb, &clock, io::Cursor::new(b"fff.".to_vec())).is_ok());
+		assert_eq!(assert_final_state(&mut cb, "open"), Ok(()));
+		assert_eq!(cb.get_buffer().get_cursor(), 1);
+	}
+
+	#[test]
+	fn is_narrow_terminal_test() {
+		assert!(is_narrow_terminal(Some(String::from("80"))));
+		assert!(is_narrow_terminal(Some(String::from("42\n"))));
+		assert!(!is_narrow_terminal(Some(String::from("81"))));
+		assert!(!is_narrow_terminal(Some(String::from("wide"))));
+		assert!(!is_narrow_terminal(None));
+	}
+
+	#[test]
+	fn list_presets_test() {
+		let presets = list_presets();
+		assert!(presets.contains("aggressive:\n  buffer_size=5 buffer_span_duration=10s min_eval_size=20 error_threshold=5 retry_timeout=30s trial_success_required=10\n"));
+		for name in Settings::PRESETS {
+			assert!(presets.contains(&format!("{name}:")), "Preset '{name}' not listed");
+		}
+	}
 
 	#[test]
 	fn help_test() {
@@ -55,4 +295,13 @@ mod test {
 			assert!(help.contains(field), "Field name '{}' not found in help", field);
 		}
 	}
+
+	#[test]
+	fn stats_summary_test() {
+		let mut cb = CircuitBreaker::default();
+		cb.record::<(), ()>(Ok(()));
+		cb.record::<(), ()>(Ok(()));
+		cb.record::<(), ()>(Err(()));
+		assert_eq!(stats_summary(&cb), "Successes: 2\nFailures: 1\nTrips: 0\nTime open: 0s\n");
+	}
 }
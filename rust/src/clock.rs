@@ -0,0 +1,104 @@
+//! The source of time a [CircuitBreaker](crate::CircuitBreaker) reads from
+//!
+//! With a [ManualClock] the [CircuitBreaker](crate::CircuitBreaker) is fully
+//! deterministic: replaying the same sequence of events and clock advances
+//! against a breaker with the same [Settings](crate::Settings) always results in
+//! the same state, buffer layout and `summary_json`. This makes it possible to
+//! backtest settings against recorded traffic.
+use std::{
+	fmt,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// A source of the current time. Clones of a
+/// [CircuitBreaker](crate::CircuitBreaker) share its clock.
+pub trait Clock: fmt::Debug + Send + Sync {
+	/// Returns the current time
+	fn now(&self) -> Instant;
+}
+
+/// The default [Clock] reading the system time via `Instant::now`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+}
+
+/// A [Clock] that only moves when advanced manually.
+///
+/// Clones share the same time so a single clock can drive several breakers.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+	now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+	/// Create a new manual clock starting at `start`
+	pub fn new(start: Instant) -> Self {
+		Self {
+			now: Arc::new(Mutex::new(start)),
+		}
+	}
+
+	/// Move the clock forward by `duration`
+	pub fn advance(&self, duration: Duration) {
+		let mut now = self.now.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+		*now = now.checked_add(duration).expect("Advancing the manual clock overflowed Instant");
+	}
+}
+
+impl Default for ManualClock {
+	fn default() -> Self {
+		Self::new(Instant::now())
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> Instant {
+		*self.now.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+/// The shared [Clock] held by a [CircuitBreaker](crate::CircuitBreaker).
+///
+/// Clocks can't be compared, so they always compare equal to not affect the
+/// comparison of the breaker holding them. Cloning shares the same clock.
+#[derive(Debug, Clone)]
+pub(crate) struct BoxedClock(Arc<dyn Clock>);
+
+impl BoxedClock {
+	pub(crate) fn new(clock: impl Clock + 'static) -> Self {
+		Self(Arc::new(clock))
+	}
+
+	pub(crate) fn now(&self) -> Instant {
+		self.0.now()
+	}
+}
+
+impl PartialEq for BoxedClock {
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn manual_clock_test() {
+		let start = Instant::now();
+		let clock = ManualClock::new(start);
+		assert_eq!(clock.now(), start);
+
+		let shared = clock.clone();
+		shared.advance(Duration::from_secs(5));
+		assert_eq!(clock.now(), start + Duration::from_secs(5));
+		assert_eq!(shared.now(), clock.now());
+	}
+}
@@ -0,0 +1,180 @@
+//! The errors of this crate
+use std::{error::Error, fmt, time::Duration};
+
+/// Errors for [Settings](crate::Settings) a [CircuitBreaker](crate::CircuitBreaker)
+/// can't be created with
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsError {
+	/// The ring buffer needs at least one node
+	ZeroBufferSize,
+	/// The ring buffer has more nodes than `max_buffer_size` allows
+	BufferSizeTooLarge {
+		/// The requested `buffer_size`
+		size: usize,
+		/// The `max_buffer_size`
+		max: usize,
+	},
+	/// The rolling log needs a capacity of at least one event
+	ZeroRollingLogCapacity,
+	/// The rolling log holds more events than `max_buffer_size` allows
+	RollingLogCapacityTooLarge {
+		/// The requested capacity
+		capacity: usize,
+		/// The `max_buffer_size`
+		max: usize,
+	},
+	/// The half-open probe buffer needs at least one node
+	ZeroHalfOpenBufferSize,
+	/// The half-open probe buffer has more nodes than `max_buffer_size` allows
+	HalfOpenBufferSizeTooLarge {
+		/// The requested `half_open_buffer_size`
+		size: usize,
+		/// The `max_buffer_size`
+		max: usize,
+	},
+	/// `DenominatorPolicy::LastN` needs at least one span
+	ZeroLastNSpans,
+	/// The error threshold has to be a percentage of at least 0
+	InvalidErrorThreshold(f32),
+	/// An environment variable read by
+	/// [Settings::from_env](crate::Settings::from_env) couldn't be parsed
+	InvalidEnvVar {
+		/// The name of the variable
+		name: String,
+		/// The value we couldn't parse
+		value: String,
+	},
+}
+
+impl fmt::Display for SettingsError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SettingsError::ZeroBufferSize => write!(f, "The buffer_size must be at least 1"),
+			SettingsError::BufferSizeTooLarge { size, max } => {
+				write!(f, "The buffer_size of {size} exceeds the max_buffer_size of {max}")
+			},
+			SettingsError::ZeroRollingLogCapacity => write!(f, "The rolling log capacity must be at least 1"),
+			SettingsError::RollingLogCapacityTooLarge { capacity, max } => {
+				write!(f, "The rolling log capacity of {capacity} exceeds the max_buffer_size of {max}")
+			},
+			SettingsError::ZeroHalfOpenBufferSize => write!(f, "The half_open_buffer_size must be at least 1"),
+			SettingsError::HalfOpenBufferSizeTooLarge { size, max } => {
+				write!(f, "The half_open_buffer_size of {size} exceeds the max_buffer_size of {max}")
+			},
+			SettingsError::ZeroLastNSpans => write!(f, "The last_n denominator policy needs at least 1 span"),
+			SettingsError::InvalidErrorThreshold(threshold) => {
+				write!(f, "The error_threshold must be a percentage of at least 0 but was {threshold}")
+			},
+			SettingsError::InvalidEnvVar { name, value } => {
+				write!(f, "The environment variable {name} has an invalid value \"{value}\"")
+			},
+		}
+	}
+}
+
+impl Error for SettingsError {}
+
+/// The error returned by [CircuitBreaker::acquire](crate::CircuitBreaker::acquire)
+/// when the circuit is open
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rejected {
+	/// The time left until the circuit moves to half-open and a call may be
+	/// attempted again
+	pub retry_after: Duration,
+}
+
+impl fmt::Display for Rejected {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "The circuit is open, retry after {}ms", self.retry_after.as_millis())
+	}
+}
+
+impl Error for Rejected {}
+
+/// Errors returned by [CircuitBreaker::call](crate::CircuitBreaker::call)
+#[derive(Debug, Clone, PartialEq)]
+pub enum CircuitError<E> {
+	/// The circuit is open so the call was not made
+	Open,
+	/// The call was made and failed with this error
+	Inner(E),
+}
+
+impl<E: fmt::Display> fmt::Display for CircuitError<E> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			CircuitError::Open => write!(f, "The circuit is open"),
+			CircuitError::Inner(error) => write!(f, "{error}"),
+		}
+	}
+}
+
+impl<E: Error + 'static> Error for CircuitError<E> {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			CircuitError::Open => None,
+			CircuitError::Inner(error) => Some(error),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn settings_error_test() {
+		let error: Box<dyn Error> = Box::new(SettingsError::ZeroBufferSize);
+		assert_eq!(error.to_string(), "The buffer_size must be at least 1");
+
+		let error: Box<dyn Error> = Box::new(SettingsError::BufferSizeTooLarge {
+			size: 100_000_000,
+			max: 1_000_000,
+		});
+		assert_eq!(error.to_string(), "The buffer_size of 100000000 exceeds the max_buffer_size of 1000000");
+
+		let error: Box<dyn Error> = Box::new(SettingsError::HalfOpenBufferSizeTooLarge { size: 8, max: 4 });
+		assert_eq!(error.to_string(), "The half_open_buffer_size of 8 exceeds the max_buffer_size of 4");
+
+		let error: Box<dyn Error> = Box::new(SettingsError::ZeroRollingLogCapacity);
+		assert_eq!(error.to_string(), "The rolling log capacity must be at least 1");
+
+		let error: Box<dyn Error> = Box::new(SettingsError::InvalidErrorThreshold(-1.5));
+		assert_eq!(error.to_string(), "The error_threshold must be a percentage of at least 0 but was -1.5");
+
+		let error: Box<dyn Error> = Box::new(SettingsError::InvalidEnvVar {
+			name: String::from("CB_BUFFER_SIZE"),
+			value: String::from("many"),
+		});
+		assert_eq!(error.to_string(), "The environment variable CB_BUFFER_SIZE has an invalid value \"many\"");
+	}
+
+	#[test]
+	fn circuit_error_test() {
+		let error: Box<dyn Error> = Box::new(CircuitError::<SettingsError>::Open);
+		assert_eq!(error.to_string(), "The circuit is open");
+		assert!(error.source().is_none());
+
+		let error: Box<dyn Error> = Box::new(CircuitError::Inner(SettingsError::ZeroBufferSize));
+		assert_eq!(error.to_string(), "The buffer_size must be at least 1");
+		assert_eq!(error.source().unwrap().to_string(), "The buffer_size must be at least 1");
+	}
+
+	#[test]
+	fn rejected_test() {
+		let error: Box<dyn Error> = Box::new(Rejected {
+			retry_after: Duration::from_millis(1500),
+		});
+		assert_eq!(error.to_string(), "The circuit is open, retry after 1500ms");
+	}
+
+	#[test]
+	fn question_mark_test() {
+		fn create() -> Result<(), Box<dyn Error>> {
+			Err(SettingsError::ZeroBufferSize)?;
+			Ok(())
+		}
+
+		assert_eq!(create().unwrap_err().to_string(), "The buffer_size must be at least 1");
+	}
+}
@@ -0,0 +1,72 @@
+//! A process-wide [CircuitBreaker] for small services, behind the `global`
+//! feature, so a breaker handle doesn't have to be threaded through every
+//! function.
+//!
+//! The breaker is created with `Settings::default()` on first use unless
+//! [set_global] was called before.
+use std::sync::{Mutex, OnceLock};
+
+use crate::circuit_breaker::{CircuitBreaker, Outcome, State};
+
+static GLOBAL: OnceLock<Mutex<CircuitBreaker>> = OnceLock::new();
+
+/// Install `cb` as the global [CircuitBreaker]. Returns false, dropping `cb`,
+/// if the global breaker was already set or used.
+pub fn set_global(cb: CircuitBreaker) -> bool {
+	GLOBAL.set(Mutex::new(cb)).is_ok()
+}
+
+/// Run `f` with the global [CircuitBreaker] locked, e.g. for anything not
+/// covered by [global_record] and [global_state]
+pub fn with_global<R>(f: impl FnOnce(&mut CircuitBreaker) -> R) -> R {
+	let mut cb = GLOBAL
+		.get_or_init(|| Mutex::new(CircuitBreaker::default()))
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+	f(&mut cb)
+}
+
+/// Record `outcome` into the global [CircuitBreaker]
+pub fn global_record(outcome: Outcome) {
+	with_global(|cb| cb.record_outcome(outcome));
+}
+
+/// Get the current state of the global [CircuitBreaker]
+pub fn global_state() -> State {
+	with_global(|cb| cb.get_state())
+}
+
+#[cfg(test)]
+mod test {
+	use std::thread;
+
+	use super::*;
+	use crate::circuit_breaker::Settings;
+
+	#[test]
+	fn global_test() {
+		assert!(set_global(CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			..Settings::default()
+		})));
+		assert!(!set_global(CircuitBreaker::default()));
+
+		let handles: Vec<_> = [Outcome::Success, Outcome::Failure]
+			.into_iter()
+			.map(|outcome| {
+				thread::spawn(move || {
+					for _ in 0..50 {
+						global_record(outcome);
+					}
+				})
+			})
+			.collect();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+
+		assert_eq!(global_state(), State::Closed);
+		assert_eq!(with_global(|cb| cb.lifetime_totals()), (50, 50));
+		assert_eq!(with_global(|cb| cb.get_settings().min_eval_size), 1);
+	}
+}
@@ -0,0 +1,150 @@
+//! RAII guards recording the outcome of a call into a [CircuitBreaker] once
+//! they go out of scope
+use crate::circuit_breaker::CircuitBreaker;
+
+/// A guard for a single call obtained via [CircuitBreaker::guard].
+///
+/// The guard holds a mutable borrow of the [CircuitBreaker] until it is dropped,
+/// so no other recording or state checks can happen on the same breaker while
+/// the call is in flight. In a multi-thread or async context wrap the breaker in
+/// a Mutex and keep the lock for the lifetime of the guard.
+///
+/// If neither [CallGuard::success] nor [CallGuard::failure] was called the
+/// call is recorded as a failure on drop, which catches early returns and `?`.
+#[derive(Debug)]
+pub struct CallGuard<'a> {
+	cb: &'a mut CircuitBreaker,
+	success: bool,
+}
+
+impl<'a> CallGuard<'a> {
+	pub(crate) fn new(cb: &'a mut CircuitBreaker) -> Self {
+		Self { cb, success: false }
+	}
+
+	/// Mark the call as successful
+	pub fn success(mut self) {
+		self.success = true;
+	}
+
+	/// Mark the call as failed
+	pub fn failure(mut self) {
+		self.success = false;
+	}
+}
+
+impl Drop for CallGuard<'_> {
+	fn drop(&mut self) {
+		self.cb.record_bool(self.success);
+	}
+}
+
+/// A permit for a single call obtained via [CircuitBreaker::acquire], finalized
+/// with [Permit::ok] or [Permit::err] to record the outcome.
+///
+/// Like [CallGuard] the permit holds a mutable borrow of the [CircuitBreaker]
+/// and records a failure if it's dropped without being finalized.
+#[derive(Debug)]
+pub struct Permit<'a> {
+	guard: CallGuard<'a>,
+}
+
+impl<'a> Permit<'a> {
+	pub(crate) fn new(cb: &'a mut CircuitBreaker) -> Self {
+		Self {
+			guard: CallGuard::new(cb),
+		}
+	}
+
+	/// Record the call as successful
+	pub fn ok(self) {
+		self.guard.success();
+	}
+
+	/// Record the call as failed
+	pub fn err(self) {
+		self.guard.failure();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::{Duration, Instant};
+
+	use crate::{circuit_breaker::Settings, error::Rejected, State};
+
+	use super::*;
+
+	#[test]
+	fn success_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		cb.guard().unwrap().success();
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 1);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 0);
+	}
+
+	#[test]
+	fn failure_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		cb.guard().unwrap().failure();
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 0);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+	}
+
+	#[test]
+	fn drop_without_outcome_test() {
+		fn call(cb: &mut CircuitBreaker) -> Result<(), &str> {
+			let guard = cb.guard().ok_or("open")?;
+			Err("early return")?;
+			guard.success();
+			Ok(())
+		}
+
+		let mut cb = CircuitBreaker::new(Settings::default());
+		assert!(call(&mut cb).is_err());
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 0);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+	}
+
+	#[test]
+	fn permit_test() {
+		let mut cb = CircuitBreaker::new(Settings::default());
+		cb.acquire().unwrap().ok();
+		cb.acquire().unwrap().err();
+		drop(cb.acquire().unwrap());
+		assert_eq!(cb.get_buffer().get_node_info(0).success_count, 1);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 2);
+	}
+
+	#[test]
+	fn permit_rejected_test() {
+		let retry_timeout = Duration::from_secs(30);
+		let mut cb = CircuitBreaker::new(Settings {
+			trip_on_consecutive: Some(1),
+			retry_timeout,
+			..Settings::default()
+		});
+		cb.acquire().unwrap().err();
+		assert!(matches!(cb.get_state(), State::Open(_)));
+
+		let Err(Rejected { retry_after }) = cb.acquire() else {
+			panic!("an open circuit should reject the permit");
+		};
+		assert!(retry_after > Duration::ZERO && retry_after <= retry_timeout);
+		assert_eq!(cb.get_buffer().get_node_info(0).failure_count, 1);
+	}
+
+	#[test]
+	fn open_test() {
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut cb = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			buffer_span_duration,
+			..Settings::default()
+		});
+		cb.guard().unwrap().failure();
+		cb.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+		assert!(matches!(cb.get_state(), State::Open(_)));
+		assert!(cb.guard().is_none());
+	}
+}
@@ -0,0 +1,138 @@
+//! Callbacks a [CircuitBreaker](crate::CircuitBreaker) invokes on state
+//! transitions
+use std::fmt;
+
+use crate::circuit_breaker::State;
+
+type Hook = Box<dyn FnMut() + Send>;
+type TransitionHook = Box<dyn FnMut(State, State) + Send>;
+type RecordHook = Box<dyn FnMut(usize, usize) + Send>;
+
+/// The hooks registered on a [CircuitBreaker](crate::CircuitBreaker).
+///
+/// Hooks are boxed closures which can't be compared or cloned, so [Hooks]
+/// always compare equal and cloning them results in no hooks at all.
+#[derive(Default)]
+pub struct Hooks {
+	on_open: Vec<Hook>,
+	on_idle_reset: Vec<Hook>,
+	on_transition: Vec<TransitionHook>,
+	on_record: Vec<RecordHook>,
+}
+
+impl Hooks {
+	/// Register a hook that fires once each time the circuit opens
+	pub fn add_on_open(&mut self, hook: impl FnMut() + Send + 'static) {
+		self.on_open.push(Box::new(hook));
+	}
+
+	/// Invoke all hooks registered for the circuit opening
+	pub fn emit_open(&mut self) {
+		for hook in &mut self.on_open {
+			hook();
+		}
+	}
+
+	/// Register a hook that fires when a long idle gap clears the whole ring
+	/// buffer of a closed circuit
+	pub fn add_on_idle_reset(&mut self, hook: impl FnMut() + Send + 'static) {
+		self.on_idle_reset.push(Box::new(hook));
+	}
+
+	/// Invoke all hooks registered for the ring buffer being cleared by idling
+	pub fn emit_idle_reset(&mut self) {
+		for hook in &mut self.on_idle_reset {
+			hook();
+		}
+	}
+
+	/// Register a hook that fires on every state transition with the state we
+	/// came from and the state we moved to
+	pub fn add_on_transition(&mut self, hook: impl FnMut(State, State) + Send + 'static) {
+		self.on_transition.push(Box::new(hook));
+	}
+
+	/// Invoke all hooks registered for state transitions
+	pub fn emit_transition(&mut self, from: State, to: State) {
+		for hook in &mut self.on_transition {
+			hook(from, to);
+		}
+	}
+
+	/// Register a hook that fires whenever events are recorded with the number
+	/// of `(successes, failures)` recorded at once
+	pub fn add_on_record(&mut self, hook: impl FnMut(usize, usize) + Send + 'static) {
+		self.on_record.push(Box::new(hook));
+	}
+
+	/// Invoke all hooks registered for recorded events
+	pub fn emit_record(&mut self, successes: usize, failures: usize) {
+		for hook in &mut self.on_record {
+			hook(successes, failures);
+		}
+	}
+}
+
+impl fmt::Debug for Hooks {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Hooks")
+			.field("on_open", &self.on_open.len())
+			.field("on_idle_reset", &self.on_idle_reset.len())
+			.field("on_transition", &self.on_transition.len())
+			.field("on_record", &self.on_record.len())
+			.finish()
+	}
+}
+
+impl Clone for Hooks {
+	fn clone(&self) -> Self {
+		Self::default()
+	}
+}
+
+impl PartialEq for Hooks {
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	};
+
+	use super::*;
+
+	#[test]
+	fn emit_open_test() {
+		let count = Arc::new(AtomicUsize::new(0));
+		let mut hooks = Hooks::default();
+		hooks.emit_open();
+
+		let counter = count.clone();
+		hooks.add_on_open(move || {
+			counter.fetch_add(1, Ordering::SeqCst);
+		});
+		hooks.emit_open();
+		hooks.emit_open();
+		assert_eq!(count.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn clone_test() {
+		let mut hooks = Hooks::default();
+		hooks.add_on_open(|| {});
+		hooks.add_on_idle_reset(|| {});
+		hooks.add_on_idle_reset(|| {});
+		hooks.add_on_transition(|_, _| {});
+		hooks.add_on_record(|_, _| {});
+		assert_eq!(format!("{hooks:?}"), "Hooks { on_open: 1, on_idle_reset: 2, on_transition: 1, on_record: 1 }");
+		assert_eq!(
+			format!("{:?}", hooks.clone()),
+			"Hooks { on_open: 0, on_idle_reset: 0, on_transition: 0, on_record: 0 }"
+		);
+		assert_eq!(hooks.clone(), hooks);
+	}
+}
@@ -64,8 +64,36 @@
 #![warn(clippy::arithmetic_side_effects)]
 #![warn(arithmetic_overflow)]
 
+pub mod atomic_ring_buffer;
 pub mod circuit_breaker;
+pub mod clock;
+pub mod error;
+#[cfg(feature = "global")]
+pub mod global;
+pub mod guard;
+pub mod hooks;
+#[cfg(feature = "test-util")]
+pub mod recorder;
+pub mod registry;
 pub mod ring_buffer;
+pub mod rolling_log;
+pub mod window;
 
-pub use circuit_breaker::{CircuitBreaker, Settings, State};
-pub use ring_buffer::{Node, NodeInfo, RingBuffer};
+pub use atomic_ring_buffer::AtomicRingBuffer;
+pub use circuit_breaker::{
+	parse_duration, CircuitBreaker, DenominatorPolicy, MetricsSnapshot, OpenPolicy, OpenReason, Outcome, ProbeResult,
+	Settings, SpanView, State, StateDurations, StateEntryCounts, StateExplanation, ThresholdMode, TimingInfo,
+	TransitionRecord, TripKind,
+};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use error::{CircuitError, Rejected, SettingsError};
+#[cfg(feature = "global")]
+pub use global::{global_record, global_state, set_global, with_global};
+pub use guard::{CallGuard, Permit};
+pub use hooks::Hooks;
+#[cfg(feature = "test-util")]
+pub use recorder::{RecordedEvent, Recorder};
+pub use registry::CircuitBreakerRegistry;
+pub use ring_buffer::{AdvanceResult, Node, NodeInfo, RingBuffer};
+pub use rolling_log::RollingLog;
+pub use window::{Window, WindowMode};
@@ -1,13 +1,13 @@
 #![warn(clippy::arithmetic_side_effects)]
 #![warn(arithmetic_overflow)]
 
-mod circuit_breaker;
 mod cli_args;
 mod cli_helpers;
-mod ring_buffer;
 mod visualizer;
 
-use std::env;
+use std::{env, io};
+
+use circuitbreakers::{circuit_breaker, clock, error, window};
 
 fn main() {
 	let args: Vec<String> = env::args().skip(1).collect();
@@ -25,11 +25,49 @@ fn main() {
 		return;
 	}
 
+	if args.contains(&String::from("--list-presets")) {
+		print!("{}", cli_helpers::list_presets());
+		return;
+	}
+
 	let no_auto_play = args.contains(&String::from("-a")) || args.contains(&String::from("--noautoplay"));
+	let stats_on_exit = args.contains(&String::from("--stats-on-exit"));
+	let minimal = args.contains(&String::from("--minimal")) || cli_helpers::is_narrow_terminal(env::var("COLUMNS").ok());
 
+	let batch = args.contains(&String::from("--batch"));
+	let assert_final_state = cli_args::parse_assert_final_state(&args);
+	if assert_final_state.is_some() && !batch {
+		cli_helpers::exit_with_error("The assert-final-state flag requires the batch flag", 1);
+	}
+
+	let watch = cli_args::parse_watch(&args);
 	let settings = cli_args::parse_args(args);
-	let mut cb = circuit_breaker::CircuitBreaker::new(settings);
 
-	let mut vis = visualizer::Visualizer::new(&mut cb);
+	if batch {
+		// replay against a manual clock so "." can move on to the next span
+		let clock = clock::ManualClock::default();
+		let mut cb = circuit_breaker::CircuitBreaker::new_with_clock(settings, clock.clone());
+		if let Err(error) = cli_helpers::run_batch(&mut cb, &clock, io::stdin().lock()) {
+			cli_helpers::exit_with_error(&format!("Could not read the batch input: {error}"), 1);
+		}
+		if stats_on_exit {
+			print!("{}", cli_helpers::stats_summary(&cb));
+		}
+		if let Some(expected) = assert_final_state {
+			if let Err(error) = cli_helpers::assert_final_state(&mut cb, &expected) {
+				cli_helpers::exit_with_error(&error, 1);
+			}
+		}
+		println!("{}", cb.get_state().label());
+		return;
+	}
+
+	let mut cb = circuit_breaker::CircuitBreaker::new(settings);
+	let mut vis = visualizer::Visualizer::new(&mut cb).with_watch(watch).with_minimal(minimal);
 	let _ = vis.start(!no_auto_play);
+
+	// the terminal is restored once start returns
+	if stats_on_exit {
+		print!("{}", cli_helpers::stats_summary(&cb));
+	}
 }
@@ -0,0 +1,171 @@
+//! A [Recorder] collecting what a [CircuitBreaker] does, to keep tests of
+//! code using a breaker short. Only available with the `test-util` feature.
+//!
+//! ```rust
+//! use circuitbreakers::{CircuitBreaker, Recorder, State};
+//!
+//! let mut cb = CircuitBreaker::default();
+//! let recorder = Recorder::attach(&mut cb);
+//! cb.record_many_failures(3);
+//!
+//! assert_eq!(recorder.recorded(), (0, 3));
+//! assert_eq!(cb.get_state(), State::Closed);
+//! recorder.assert_trip_count(0);
+//! ```
+use std::sync::{Arc, Mutex};
+
+use crate::circuit_breaker::{CircuitBreaker, State};
+
+/// An event collected by a [Recorder]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordedEvent {
+	/// The circuit moved from one state to another
+	Transition { from: State, to: State },
+	/// Events were counted at once
+	Record { successes: usize, failures: usize },
+}
+
+/// Collects the transitions and recordings of a [CircuitBreaker] via its
+/// `on_transition` and `on_record` hooks
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+	events: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl Recorder {
+	/// Create a [Recorder] collecting the events of `cb` from now on
+	pub fn attach(cb: &mut CircuitBreaker) -> Self {
+		let recorder = Self::default();
+
+		let events = Arc::clone(&recorder.events);
+		cb.on_transition(move |from, to| {
+			events.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(RecordedEvent::Transition { from, to })
+		});
+		let events = Arc::clone(&recorder.events);
+		cb.on_record(move |successes, failures| {
+			events.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(RecordedEvent::Record { successes, failures })
+		});
+
+		recorder
+	}
+
+	/// Get all events collected so far, oldest first
+	pub fn events(&self) -> Vec<RecordedEvent> {
+		self.events.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+	}
+
+	/// Get the `(from, to)` states of all transitions so far, oldest first
+	pub fn transitions(&self) -> Vec<(State, State)> {
+		self
+			.events()
+			.into_iter()
+			.filter_map(|event| match event {
+				RecordedEvent::Transition { from, to } => Some((from, to)),
+				RecordedEvent::Record { .. } => None,
+			})
+			.collect()
+	}
+
+	/// Get the `(successes, failures)` counted so far
+	pub fn recorded(&self) -> (usize, usize) {
+		self.events().into_iter().fold((0, 0), |(total_successes, total_failures), event| match event {
+			RecordedEvent::Record { successes, failures } => {
+				(total_successes.saturating_add(successes), total_failures.saturating_add(failures))
+			},
+			RecordedEvent::Transition { .. } => (total_successes, total_failures),
+		})
+	}
+
+	/// Get how often the circuit opened
+	pub fn trip_count(&self) -> usize {
+		self.transitions().iter().filter(|(_, to)| matches!(to, State::Open(_))).count()
+	}
+
+	/// Forget all events collected so far
+	pub fn clear(&self) {
+		self.events.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+	}
+
+	/// Panic unless the circuit transitioned from `from` to `to` at some point.
+	/// The time of `State::Open` is ignored.
+	#[track_caller]
+	pub fn assert_transitioned(&self, from: State, to: State) {
+		let transitions = self.transitions();
+		assert!(
+			transitions.iter().any(|(f, t)| f.label() == from.label() && t.label() == to.label()),
+			"Expected a transition from {} to {}, got {transitions:?}",
+			from.label(),
+			to.label()
+		);
+	}
+
+	/// Panic unless the circuit opened exactly `count` times
+	#[track_caller]
+	pub fn assert_trip_count(&self, count: usize) {
+		assert_eq!(self.trip_count(), count, "Unexpected number of trips in {:?}", self.transitions());
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::{Duration, Instant};
+
+	use super::*;
+	use crate::{circuit_breaker::Settings, clock::ManualClock};
+
+	#[test]
+	fn recorder_test() {
+		let clock = ManualClock::default();
+		let buffer_span_duration = Duration::from_secs(1);
+		let retry_timeout = Duration::from_secs(5);
+		let mut cb = CircuitBreaker::new_with_clock(
+			Settings {
+				min_eval_size: 2,
+				buffer_span_duration,
+				retry_timeout,
+				trial_success_required: 2,
+				..Settings::default()
+			},
+			clock.clone(),
+		);
+		let recorder = Recorder::attach(&mut cb);
+
+		// trip, fail the trial, then recover
+		cb.record_many_failures(2);
+		clock.advance(buffer_span_duration);
+		cb.get_state();
+		clock.advance(retry_timeout);
+		cb.get_state();
+		cb.record::<(), ()>(Err(()));
+		clock.advance(retry_timeout);
+		cb.get_state();
+		cb.record::<(), ()>(Ok(()));
+		cb.record::<(), ()>(Ok(()));
+
+		recorder.assert_transitioned(State::Closed, State::Open(Instant::now()));
+		recorder.assert_transitioned(State::HalfOpen, State::Open(Instant::now()));
+		recorder.assert_transitioned(State::HalfOpen, State::Closed);
+		recorder.assert_trip_count(2);
+		assert_eq!(recorder.transitions().len(), 5);
+		assert_eq!(recorder.recorded(), (2, 3));
+		assert_eq!(
+			recorder.events()[0],
+			RecordedEvent::Record {
+				successes: 0,
+				failures: 2,
+			}
+		);
+
+		recorder.clear();
+		assert!(recorder.events().is_empty());
+		recorder.assert_trip_count(0);
+	}
+
+	#[test]
+	#[should_panic(expected = "Expected a transition from Half Open to Closed")]
+	fn assert_transitioned_test() {
+		let mut cb = CircuitBreaker::default();
+		let recorder = Recorder::attach(&mut cb);
+		recorder.assert_transitioned(State::HalfOpen, State::Closed);
+	}
+}
@@ -0,0 +1,86 @@
+//! A collection of named [CircuitBreaker]s, e.g. one per downstream service
+use std::collections::HashMap;
+
+use crate::circuit_breaker::{CircuitBreaker, State};
+
+/// Holds a [CircuitBreaker] per name
+#[derive(Debug, Default)]
+pub struct CircuitBreakerRegistry {
+	breakers: HashMap<String, CircuitBreaker>,
+}
+
+impl CircuitBreakerRegistry {
+	/// Create an empty registry
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add a [CircuitBreaker] under `name`, replacing and returning any breaker
+	/// previously registered under that name
+	pub fn register(&mut self, name: &str, cb: CircuitBreaker) -> Option<CircuitBreaker> {
+		self.breakers.insert(String::from(name), cb)
+	}
+
+	/// Returns the [CircuitBreaker] registered under `name`
+	pub fn get_mut(&mut self, name: &str) -> Option<&mut CircuitBreaker> {
+		self.breakers.get_mut(name)
+	}
+
+	/// Returns the number of registered breakers
+	pub fn len(&self) -> usize {
+		self.breakers.len()
+	}
+
+	/// Returns true if no breakers are registered
+	pub fn is_empty(&self) -> bool {
+		self.breakers.is_empty()
+	}
+
+	/// Returns the most severe [State] among all breakers, see [State::severity].
+	/// An empty registry is `Closed`
+	pub fn aggregate_state(&mut self) -> State {
+		self.breakers.values_mut().map(|cb| cb.get_state()).max_by_key(|state| state.severity()).unwrap_or(State::Closed)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::{Duration, Instant};
+
+	use crate::circuit_breaker::Settings;
+
+	use super::*;
+
+	#[test]
+	fn register_test() {
+		let mut registry = CircuitBreakerRegistry::new();
+		assert!(registry.is_empty());
+		assert!(registry.register("db", CircuitBreaker::new(Settings::default())).is_none());
+		assert!(registry.register("db", CircuitBreaker::new(Settings::default())).is_some());
+		assert_eq!(registry.len(), 1);
+		assert!(registry.get_mut("db").is_some());
+		assert!(registry.get_mut("cache").is_none());
+	}
+
+	#[test]
+	fn aggregate_state_test() {
+		let mut registry = CircuitBreakerRegistry::new();
+		assert_eq!(registry.aggregate_state(), State::Closed);
+
+		registry.register("db", CircuitBreaker::new(Settings::default()));
+		assert_eq!(registry.aggregate_state(), State::Closed);
+
+		let buffer_span_duration = Duration::from_secs(1);
+		let mut tripped = CircuitBreaker::new(Settings {
+			min_eval_size: 1,
+			buffer_span_duration,
+			..Settings::default()
+		});
+		tripped.record::<(), ()>(Err(()));
+		tripped.advance_buffer_for_time(Instant::now() + buffer_span_duration);
+
+		registry.register("cache", tripped);
+		registry.register("auth", CircuitBreaker::new(Settings::default()));
+		assert!(matches!(registry.aggregate_state(), State::Open(_)));
+	}
+}
@@ -1,9 +1,22 @@
 //! A rust implementation of a ring buffer without using a linked list
+use std::time::Duration;
+
+use crate::window::Window;
+
+/// The exclusive upper bounds in milliseconds of the first three latency
+/// buckets, the last bucket holds everything at or above 1s
+pub const LATENCY_BUCKET_BOUNDS_MS: [u128; 3] = [50, 200, 1000];
+
 /// The node within the [RingBuffer]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Node {
 	failure_count: usize,
 	success_count: usize,
+	/// The failures that were timeouts, already included in `failure_count`
+	timeout_count: usize,
+	/// The events recorded with a latency per bucket, see
+	/// [LATENCY_BUCKET_BOUNDS_MS]
+	latency_buckets: [usize; 4],
 }
 
 impl Node {
@@ -11,12 +24,53 @@ impl Node {
 		Self {
 			failure_count: 0,
 			success_count: 0,
+			timeout_count: 0,
+			latency_buckets: [0; 4],
 		}
 	}
 
 	pub fn reset(&mut self) {
 		self.failure_count = 0;
 		self.success_count = 0;
+		self.timeout_count = 0;
+		self.latency_buckets = [0; 4];
+	}
+
+	/// Add all counts of `other` to this node
+	fn merge(&mut self, other: &Node) {
+		self.failure_count = self.failure_count.saturating_add(other.failure_count);
+		self.success_count = self.success_count.saturating_add(other.success_count);
+		self.timeout_count = self.timeout_count.saturating_add(other.timeout_count);
+		for (bucket, count) in self.latency_buckets.iter_mut().zip(other.latency_buckets) {
+			*bucket = bucket.saturating_add(count);
+		}
+	}
+
+	/// Returns the number of events recorded into this node per latency bucket,
+	/// see [LATENCY_BUCKET_BOUNDS_MS]
+	pub fn latency_buckets(&self) -> [usize; 4] {
+		self.latency_buckets
+	}
+
+	/// Returns the number of failed events recorded into this node which were
+	/// timeouts
+	pub fn timeout_count(&self) -> usize {
+		self.timeout_count
+	}
+
+	/// Returns the number of successful events recorded into this node
+	pub fn success_count(&self) -> usize {
+		self.success_count
+	}
+
+	/// Returns the number of failed events recorded into this node
+	pub fn failure_count(&self) -> usize {
+		self.failure_count
+	}
+
+	/// Returns the number of all events recorded into this node
+	pub fn total(&self) -> usize {
+		self.failure_count.saturating_add(self.success_count)
 	}
 }
 
@@ -33,8 +87,37 @@ pub struct NodeInfo {
 	pub success_count: usize,
 }
 
+impl NodeInfo {
+	/// Returns the number of all events of the node
+	pub fn total(&self) -> usize {
+		self.failure_count.saturating_add(self.success_count)
+	}
+
+	/// Returns the error rate of the node as a percentage (0.0 to 100.0) or 0.0
+	/// if it has no events
+	pub fn error_rate(&self) -> f32 {
+		match self.total() {
+			0 => 0.0,
+			total => percentage(self.failure_count, total),
+		}
+	}
+}
+
+/// Describes what happened during [RingBuffer::advance]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdvanceResult {
+	/// The cursor after advancing
+	pub new_cursor: usize,
+	/// The number of spans we moved forward
+	pub spans_moved: usize,
+	/// True if we moved past the last node and started at the front again
+	pub wrapped: bool,
+	/// True if every node was reset
+	pub cleared_all: bool,
+}
+
 /// The main ring buffer struct
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RingBuffer {
 	cursor: usize,
 	nodes: Vec<Node>,
@@ -58,36 +141,138 @@ impl RingBuffer {
 		self.nodes.len()
 	}
 
+	/// Returns true if no node holds any events, regardless of the size of the
+	/// buffer
+	pub fn has_no_events(&self) -> bool {
+		self.nodes.iter().all(|node| node.total() == 0)
+	}
+
 	/// Returns the current cursor
 	pub fn get_cursor(&self) -> usize {
 		self.cursor
 	}
 
+	/// Returns the index the cursor will move to on the next single advance
+	// Allowing modulo with size in this method because size can't be less than 1
+	// or we panic on creation of the buffer in the new method
+	#[allow(clippy::arithmetic_side_effects)]
+	pub fn peek_next_cursor(&self) -> usize {
+		self.cursor.saturating_add(1) % self.get_size()
+	}
+
+	/// Place the cursor at `index` without touching any node data, unlike
+	/// `advance` which resets the nodes it moves over
+	pub fn rotate_to(&mut self, index: usize) {
+		if index >= self.nodes.len() {
+			panic!("Index out of bounds");
+		}
+
+		self.cursor = index;
+	}
+
 	/// Move the cursor forward by `steps` positions (modulo buffer size),
 	/// resetting any nodes we skip along the way
+	///
+	/// The node we land on is always reset as well. It holds the data of the
+	/// oldest span from the previous lap around the buffer, so without the reset
+	/// stale data would leak into the new current span:
+	/// - `steps < size`: the skipped nodes and the landing node are reset, all
+	///   other nodes keep their data
+	/// - `steps == size`: we land on the same node after a full lap and every
+	///   node is reset
+	/// - `steps > size`: every node is reset and the cursor ends up at
+	///   `(cursor + steps) % size`
+	///
+	/// Advancing by `size` or more steps clears the whole buffer, so any `steps`
+	/// is safe to pass, even `usize::MAX`, without overflowing.
 	// Aloowing modulo with size in this method because size can't be less than 1
 	// or we panic on creation of the buffer in the new method
 	#[allow(clippy::arithmetic_side_effects)]
-	pub fn advance(&mut self, steps: usize) {
+	pub fn advance(&mut self, steps: usize) -> AdvanceResult {
 		let size = self.get_size();
+		let cleared_all = steps >= size;
+		// cursor < size so this can't overflow if steps < size
+		let wrapped = cleared_all || self.cursor + steps >= size;
 
-		let start = self.cursor.saturating_add(1);
-		let end = self.cursor.saturating_add(steps).saturating_add(1);
 		if steps >= size {
 			for node in &mut self.nodes {
 				node.reset();
 			}
 		} else {
-			for idx in start..end {
+			// steps < size here so this can't overflow
+			for idx in self.cursor + 1..=self.cursor + steps {
 				// size > 0 here so safe to divide with
 				let skip_idx = idx % size;
 				self.nodes[skip_idx].reset();
 			}
 		}
 
-		// size > 0 here so safe to divide with
-		self.cursor = self.cursor.saturating_add(steps) % size;
+		// size > 0 here so safe to divide with and both operands are < size
+		self.cursor = (self.cursor + steps % size) % size;
 		self.nodes[self.cursor].reset();
+
+		AdvanceResult {
+			new_cursor: self.cursor,
+			spans_moved: steps,
+			wrapped,
+			cleared_all,
+		}
+	}
+
+	/// Change the number of nodes while keeping the data of the most recent
+	/// nodes. The current node stays the current node, if we shrink the oldest
+	/// nodes are dropped and if we grow empty nodes are added after the cursor.
+	// Allowing modulo with size in this method because size can't be less than 1
+	#[allow(clippy::arithmetic_side_effects)]
+	pub fn resize(&mut self, elements: usize) {
+		if elements == 0 {
+			panic!("You must at least have one buffer node in your ring buffer");
+		}
+
+		let size = self.get_size();
+		// oldest to newest with the current node last
+		let kept = size.min(elements);
+		let mut nodes: Vec<Node> = (0..kept).rev().map(|age| self.nodes[(self.cursor + size - age) % size]).collect();
+		nodes.resize(elements, Node::new());
+
+		self.cursor = kept - 1;
+		self.nodes = nodes;
+	}
+
+	/// Merge runs of adjacent completed nodes which each hold fewer than
+	/// `min_events` events into the newest node of the run, summing their
+	/// counts, and return how many nodes were emptied. Empty nodes are part of
+	/// the runs and the current node is left alone.
+	///
+	/// This reshapes the window: the overall error rate stays the same but
+	/// sparse traffic is evaluated in fewer, denser units, e.g. by `worst_node`.
+	/// The merged events also live as long as the node they were merged into.
+	// Allowing modulo with size in this method because size can't be less than 1
+	#[allow(clippy::arithmetic_side_effects)]
+	pub fn compact(&mut self, min_events: usize) -> usize {
+		let size = self.get_size();
+		let mut emptied = 0;
+		// the node the run so far was merged into
+		let mut run: Option<usize> = None;
+
+		// oldest to newest without the current node
+		for age in (1..size).rev() {
+			let index = (self.cursor + size - age) % size;
+			if self.nodes[index].total() >= min_events {
+				run = None;
+				continue;
+			}
+
+			if let Some(previous) = run.filter(|previous| self.nodes[*previous].total() > 0) {
+				let merged = self.nodes[previous];
+				self.nodes[index].merge(&merged);
+				self.nodes[previous].reset();
+				emptied += 1;
+			}
+			run = Some(index);
+		}
+
+		emptied
 	}
 
 	/// Increments the failure count at the current cursor
@@ -95,11 +280,73 @@ impl RingBuffer {
 		self.nodes[self.cursor].failure_count = self.nodes[self.cursor].failure_count.saturating_add(1);
 	}
 
+	/// Increments the failure count at the current cursor and tallies the
+	/// failure as a timeout
+	pub fn add_timeout(&mut self) {
+		self.add_failure();
+		self.tally_timeouts(1);
+	}
+
+	/// Tally `count` failures already recorded into the current node as timeouts
+	pub(crate) fn tally_timeouts(&mut self, count: usize) {
+		self.nodes[self.cursor].timeout_count = self.nodes[self.cursor].timeout_count.saturating_add(count);
+	}
+
+	/// Returns the number of timeouts across all nodes
+	pub fn get_timeout_count(&self) -> usize {
+		self.nodes.iter().fold(0_usize, |total, node| total.saturating_add(node.timeout_count))
+	}
+
+	/// Tally an event already recorded into the current node in the bucket for
+	/// `latency`, see [LATENCY_BUCKET_BOUNDS_MS]
+	pub fn add_latency(&mut self, latency: Duration) {
+		let millis = latency.as_millis();
+		let bucket = LATENCY_BUCKET_BOUNDS_MS.iter().position(|bound| millis < *bound).unwrap_or(3);
+		let buckets = &mut self.nodes[self.cursor].latency_buckets;
+		buckets[bucket] = buckets[bucket].saturating_add(1);
+	}
+
+	/// Returns the number of events per latency bucket across all nodes, see
+	/// [LATENCY_BUCKET_BOUNDS_MS]
+	pub fn latency_distribution(&self) -> [usize; 4] {
+		self.nodes.iter().fold([0; 4], |mut distribution, node| {
+			for (total, count) in distribution.iter_mut().zip(node.latency_buckets) {
+				*total = total.saturating_add(count);
+			}
+			distribution
+		})
+	}
+
 	/// Increments the success count at the current cursor
 	pub fn add_success(&mut self) {
 		self.nodes[self.cursor].success_count = self.nodes[self.cursor].success_count.saturating_add(1);
 	}
 
+	/// Increments the failure count at the current cursor by `count`
+	pub fn add_failures(&mut self, count: usize) {
+		self.nodes[self.cursor].failure_count = self.nodes[self.cursor].failure_count.saturating_add(count);
+	}
+
+	/// Increments the success count at the current cursor by `count`
+	pub fn add_successes(&mut self, count: usize) {
+		self.nodes[self.cursor].success_count = self.nodes[self.cursor].success_count.saturating_add(count);
+	}
+
+	/// Fold over all nodes with their index, starting at index 0, to build
+	/// custom aggregations
+	pub fn fold_nodes<B, F: FnMut(B, usize, NodeInfo) -> B>(&self, init: B, mut f: F) -> B {
+		self.nodes.iter().enumerate().fold(init, |acc, (i, node)| {
+			f(
+				acc,
+				i,
+				NodeInfo {
+					failure_count: node.failure_count,
+					success_count: node.success_count,
+				},
+			)
+		})
+	}
+
 	/// Retrieve info for a specific node
 	pub fn get_node_info(&self, index: usize) -> NodeInfo {
 		if index >= self.nodes.len() {
@@ -112,30 +359,258 @@ impl RingBuffer {
 		}
 	}
 
+	/// Returns the number of events the error rate is calculated from, which
+	/// skips the current node
+	pub fn evaluated_events(&self) -> usize {
+		self.fold_nodes(0_usize, |total, i, info| {
+			if i == self.cursor {
+				total
+			} else {
+				total.saturating_add(info.total())
+			}
+		})
+	}
+
+	/// Returns the events of the `spans` nodes before the current node and, if
+	/// `include_current`, the current node, the same nodes
+	/// `get_error_rate_trailing` is calculated from
+	pub fn evaluated_events_trailing(&self, spans: usize, include_current: bool) -> usize {
+		let (failures, successes) = self.counts(include_current, spans);
+		failures.saturating_add(successes)
+	}
+
+	/// Returns the `(failures, total)` events the error rate is calculated from
+	/// If `failures+successes` < `min_eval_size` or zero, returns None
+	///
+	/// Skips the current node
+	pub fn error_rate_parts(&self, min_eval_size: usize) -> Option<(usize, usize)> {
+		self.parts(min_eval_size, false)
+	}
+
+	fn parts(&self, min_eval_size: usize, include_current: bool) -> Option<(usize, usize)> {
+		self.trailing_parts(min_eval_size, usize::MAX, include_current)
+	}
+
+	fn trailing_parts(&self, min_eval_size: usize, spans: usize, include_current: bool) -> Option<(usize, usize)> {
+		let (failures, successes) = self.counts(include_current, spans);
+		Self::checked_parts(failures, successes, min_eval_size)
+	}
+
+	/// Returns the `(failures, successes)` of the `spans` nodes before the
+	/// current node and, if `include_current`, the current node
+	// Allowing the modulo with size because size can't be less than 1 and the
+	// cursor and index are always below size
+	#[allow(clippy::arithmetic_side_effects)]
+	fn counts(&self, include_current: bool, spans: usize) -> (usize, usize) {
+		let size = self.get_size();
+		self.fold_nodes((0_usize, 0_usize), |(failures, successes), i, info| {
+			// how many spans ago the node was current
+			let age = (self.cursor + size - i) % size;
+			if (age == 0 && !include_current) || age > spans {
+				(failures, successes)
+			} else {
+				(failures.saturating_add(info.failure_count), successes.saturating_add(info.success_count))
+			}
+		})
+	}
+
+	fn checked_parts(failures: usize, successes: usize, min_eval_size: usize) -> Option<(usize, usize)> {
+		let total = failures.saturating_add(successes);
+		if total < min_eval_size || total == 0 {
+			None
+		} else {
+			Some((failures, total))
+		}
+	}
+
 	/// Returns the error rate as a percentage (0.0 to 100.0)
 	/// If `failures+successes` < `min_eval_size`, returns 0.0
 	///
 	/// Skips nodes with less than min_eval_size and the current node
 	pub fn get_error_rate(&self, min_eval_size: usize) -> f32 {
-		let mut failures: usize = 0;
-		let mut successes: usize = 0;
+		match self.error_rate_parts(min_eval_size) {
+			None => 0.0,
+			Some((failures, total)) => percentage(failures, total),
+		}
+	}
+
+	/// Returns the error rate as a percentage (0.0 to 100.0) of only the `spans`
+	/// most recently completed nodes before the current node, and the current
+	/// node if `include_current`. If `failures+successes` < `min_eval_size`,
+	/// returns 0.0
+	pub fn get_error_rate_trailing(&self, min_eval_size: usize, spans: usize, include_current: bool) -> f32 {
+		match self.trailing_parts(min_eval_size, spans, include_current) {
+			None => 0.0,
+			Some((failures, total)) => percentage(failures, total),
+		}
+	}
+
+	/// Returns the error rate as a percentage (0.0 to 100.0) including the
+	/// current node. If `failures+successes` < `min_eval_size`, returns 0.0
+	pub fn get_error_rate_including_current(&self, min_eval_size: usize) -> f32 {
+		match self.parts(min_eval_size, true) {
+			None => 0.0,
+			Some((failures, total)) => percentage(failures, total),
+		}
+	}
+
+	/// Returns the error rate as a percentage (0.0 to 100.0) if one more
+	/// failure was recorded into the current node, without recording it. As the
+	/// failure lands in the current node, the rate includes it like
+	/// `get_error_rate_including_current`.
+	pub fn error_rate_if_failure(&self, min_eval_size: usize) -> f32 {
+		let (failures, successes) = self.counts(true, usize::MAX);
+		match Self::checked_parts(failures.saturating_add(1), successes, min_eval_size) {
+			None => 0.0,
+			Some((failures, total)) => percentage(failures, total),
+		}
+	}
+
+	/// Returns the error rate as a percentage (0.0 to 100.0) if one more
+	/// success was recorded into the current node, without recording it. See
+	/// `error_rate_if_failure`.
+	pub fn error_rate_if_success(&self, min_eval_size: usize) -> f32 {
+		let (failures, successes) = self.counts(true, usize::MAX);
+		match Self::checked_parts(failures, successes.saturating_add(1), min_eval_size) {
+			None => 0.0,
+			Some((failures, total)) => percentage(failures, total),
+		}
+	}
+
+	/// Returns an aligned plain text table of each node's counts and error rate,
+	/// marking the current node with `*`
+	pub fn render_table(&self) -> String {
+		self.fold_nodes(String::from("  index  success  failure    rate\n"), |mut table, i, info| {
+			let marker = if i == self.cursor { '*' } else { ' ' };
+			table.push_str(&format!(
+				"{marker}{i:>6}  {:>7}  {:>7}  {:>6.2}%\n",
+				info.success_count,
+				info.failure_count,
+				info.error_rate()
+			));
+			table
+		})
+	}
+
+	/// Compares the per-node counts of both buffers starting at each cursor, so
+	/// buffers holding the same data compare equal no matter where their cursor
+	/// is, e.g. a restored buffer that rotated. Unlike `==` which also compares
+	/// the absolute cursor.
+	pub fn counts_eq(&self, other: &RingBuffer) -> bool {
+		self.get_size() == other.get_size()
+			&& self.cursor_relative_nodes().zip(other.cursor_relative_nodes()).all(|(a, b)| a == b)
+	}
+
+	/// Add the counts of every node of `other` to the node of the same age in
+	/// this buffer, so the current nodes are merged no matter where each cursor
+	/// is. Returns false and leaves this buffer unchanged if the sizes differ.
+	pub fn merge(&mut self, other: &RingBuffer) -> bool {
+		if self.get_size() != other.get_size() {
+			return false;
+		}
+
+		let (before_cursor, from_cursor) = self.nodes.split_at_mut(self.cursor);
+		for (node, other_node) in from_cursor.iter_mut().chain(before_cursor).zip(other.cursor_relative_nodes()) {
+			node.merge(other_node);
+		}
+		true
+	}
+
+	/// Returns the info of all nodes ordered by age, starting with the oldest
+	/// node just after the cursor and ending with the current node
+	pub fn oldest_to_newest(&self) -> Vec<NodeInfo> {
+		self
+			.nodes
+			.iter()
+			.cycle()
+			.skip(self.cursor.saturating_add(1))
+			.take(self.nodes.len())
+			.map(|node| NodeInfo {
+				failure_count: node.failure_count,
+				success_count: node.success_count,
+			})
+			.collect()
+	}
+
+	/// Iterates the nodes starting at the cursor
+	fn cursor_relative_nodes(&self) -> impl Iterator<Item = &Node> {
+		self.nodes.iter().cycle().skip(self.cursor).take(self.nodes.len())
+	}
+
+	/// Returns the average number of events per span, skipping the current node
+	/// and empty nodes, or 0.0 if there are none, e.g. to tune `min_eval_size`
+	/// to the observed load
+	pub fn average_events_per_span(&self) -> f32 {
+		let (spans, events) = self
+			.nodes
+			.iter()
+			.enumerate()
+			.filter(|(i, node)| *i != self.cursor && node.total() > 0)
+			.fold((0_usize, 0_usize), |(spans, events), (_, node)| {
+				(spans.saturating_add(1), events.saturating_add(node.total()))
+			});
+
+		match spans {
+			0 => 0.0,
+			spans => events as f32 / spans as f32,
+		}
+	}
+
+	/// Returns the index and error rate of the node with the highest error rate
+	///
+	/// Skips the current node and nodes with less than `min_eval_size` or no
+	/// events
+	pub fn worst_node(&self, min_eval_size: usize) -> Option<(usize, f32)> {
+		let mut worst: Option<(usize, f32)> = None;
 
 		for (i, node) in self.nodes.iter().enumerate() {
-			if i == self.cursor {
+			let total = node.total();
+			if i == self.cursor || total == 0 || total < min_eval_size {
 				continue;
 			}
 
-			if node.failure_count.saturating_add(node.success_count) != 0 {
-				failures = failures.saturating_add(node.failure_count);
-				successes = successes.saturating_add(node.success_count);
+			let rate = percentage(node.failure_count, total);
+			let is_worse = match worst {
+				Some((_, worst_rate)) => rate > worst_rate,
+				None => true,
+			};
+			if is_worse {
+				worst = Some((i, rate));
 			}
 		}
 
-		if failures.saturating_add(successes) < min_eval_size || failures.saturating_add(successes) == 0 {
-			0.0
-		} else {
-			((failures as f32 / (failures.saturating_add(successes)) as f32) * 10_000.0).round() / 100.0
-		}
+		worst
+	}
+}
+
+/// The percentage of `part` in `total` rounded to two decimals
+pub(crate) fn percentage(part: usize, total: usize) -> f32 {
+	((part as f32 / total as f32) * 10_000.0).round() / 100.0
+}
+
+impl Window for RingBuffer {
+	fn add_success(&mut self) {
+		RingBuffer::add_success(self);
+	}
+
+	fn add_failure(&mut self) {
+		RingBuffer::add_failure(self);
+	}
+
+	fn add_successes(&mut self, count: usize) {
+		RingBuffer::add_successes(self, count);
+	}
+
+	fn add_failures(&mut self, count: usize) {
+		RingBuffer::add_failures(self, count);
+	}
+
+	fn evaluated_events(&self) -> usize {
+		RingBuffer::evaluated_events(self)
+	}
+
+	fn get_error_rate(&self, min_eval_size: usize) -> f32 {
+		RingBuffer::get_error_rate(self, min_eval_size)
 	}
 }
 
@@ -143,6 +618,295 @@ impl RingBuffer {
 mod test {
 	use super::*;
 
+	#[test]
+	fn node_accessors_test() {
+		let mut node = Node::new();
+		assert_eq!(node.total(), 0);
+		node.failure_count = 2;
+		node.success_count = 3;
+		assert_eq!(node.failure_count(), 2);
+		assert_eq!(node.success_count(), 3);
+		assert_eq!(node.total(), 5);
+		node.reset();
+		assert_eq!(node.total(), 0);
+	}
+
+	#[test]
+	fn evaluated_events_test() {
+		let mut buffer = RingBuffer::new(3);
+		buffer.add_successes(2);
+		assert_eq!(buffer.evaluated_events(), 0);
+		buffer.advance(1);
+		buffer.add_failures(3);
+		assert_eq!(buffer.evaluated_events(), 2);
+		buffer.advance(1);
+		assert_eq!(buffer.evaluated_events(), 5);
+		assert_eq!(buffer.evaluated_events_trailing(1, false), 3);
+		assert_eq!(buffer.evaluated_events_trailing(1, true), 3);
+		buffer.add_failure();
+		assert_eq!(buffer.evaluated_events_trailing(usize::MAX, true), 6);
+	}
+
+	#[test]
+	fn compact_test() {
+		let mut buffer = RingBuffer::new(6);
+		for (failures, successes) in [(1, 1), (0, 2), (1, 0), (0, 10), (2, 1)] {
+			buffer.add_failures(failures);
+			buffer.add_successes(successes);
+			buffer.advance(1);
+		}
+		buffer.add_failure();
+		let error_rate = buffer.get_error_rate(1);
+		assert_eq!(buffer.worst_node(3), Some((4, 66.67)));
+
+		// the first three sparse spans merge into the third, the dense fourth
+		// and the fifth with 3 events stay
+		assert_eq!(buffer.compact(4), 2);
+		assert_eq!(buffer.get_node_info(0).total(), 0);
+		assert_eq!(buffer.get_node_info(1).total(), 0);
+		assert_eq!(
+			buffer.get_node_info(2),
+			NodeInfo {
+				failure_count: 2,
+				success_count: 3,
+			}
+		);
+		assert_eq!(buffer.get_node_info(3).success_count, 10);
+		assert_eq!(buffer.get_node_info(4).total(), 3);
+		// the current node isn't merged
+		assert_eq!(buffer.get_node_info(5).failure_count, 1);
+
+		assert_eq!(buffer.get_error_rate(1), error_rate);
+		assert_eq!(buffer.worst_node(4), Some((2, 40.0)));
+		assert_eq!(buffer.compact(4), 0);
+	}
+
+	#[test]
+	fn get_error_rate_trailing_test() {
+		let mut buffer = RingBuffer::new(4);
+		buffer.rotate_to(2);
+		buffer.add_failures(4);
+		buffer.advance(1);
+		buffer.add_successes(4);
+		buffer.advance(1);
+		buffer.add_failures(2);
+		buffer.add_successes(2);
+		buffer.advance(1);
+		buffer.add_failures(1);
+
+		// the cursor is at 1 and node 0 completed last
+		assert_eq!(buffer.get_error_rate_trailing(1, 1, false), 50.0);
+		assert_eq!(buffer.get_error_rate_trailing(1, 2, false), 25.0);
+		assert_eq!(buffer.get_error_rate_trailing(1, 3, false), buffer.get_error_rate(1));
+		assert_eq!(buffer.get_error_rate_trailing(1, usize::MAX, false), 50.0);
+		assert_eq!(buffer.get_error_rate_trailing(1, 1, true), 60.0);
+		assert_eq!(buffer.get_error_rate_trailing(1, 0, true), 100.0);
+		assert_eq!(buffer.get_error_rate_trailing(1, 0, false), 0.0);
+		assert_eq!(buffer.get_error_rate_trailing(5, 1, false), 0.0);
+	}
+
+	#[test]
+	fn error_rate_if_test() {
+		let mut buffer = RingBuffer::new(3);
+		buffer.add_failures(1);
+		buffer.add_successes(8);
+		buffer.advance(1);
+		buffer.add_failures(1);
+		let before = buffer.clone();
+
+		// 2 of 10 events failed, one more failure or success tips the rate
+		assert_eq!(buffer.get_error_rate_including_current(10), 20.0);
+		assert_eq!(buffer.error_rate_if_failure(10), 27.27);
+		assert_eq!(buffer.error_rate_if_success(10), 18.18);
+		assert_eq!(buffer, before);
+
+		// the hypothetical event counts towards min_eval_size
+		assert_eq!(buffer.error_rate_if_failure(11), 27.27);
+		assert_eq!(buffer.error_rate_if_success(12), 0.0);
+		assert_eq!(RingBuffer::new(1).error_rate_if_failure(1), 100.0);
+		assert_eq!(RingBuffer::new(1).error_rate_if_success(1), 0.0);
+	}
+
+	#[test]
+	fn latency_distribution_test() {
+		let mut buffer = RingBuffer::new(2);
+		buffer.add_latency(Duration::ZERO);
+		buffer.add_latency(Duration::from_millis(49));
+		buffer.add_latency(Duration::from_millis(50));
+		buffer.advance(1);
+		buffer.add_latency(Duration::from_millis(999));
+		buffer.add_latency(Duration::from_secs(1));
+		buffer.add_latency(Duration::from_secs(30));
+		assert_eq!(buffer.nodes[0].latency_buckets(), [2, 1, 0, 0]);
+		assert_eq!(buffer.latency_distribution(), [2, 1, 1, 2]);
+
+		buffer.advance(1);
+		assert_eq!(buffer.latency_distribution(), [0, 0, 1, 2]);
+	}
+
+	#[test]
+	fn counts_eq_test() {
+		let mut buffer = RingBuffer::new(3);
+		buffer.add_failure();
+		buffer.advance(1);
+		buffer.add_successes(2);
+
+		let mut rotated = RingBuffer::new(3);
+		rotated.rotate_to(2);
+		rotated.add_failure();
+		rotated.advance(1);
+		rotated.add_successes(2);
+
+		assert_eq!(buffer.get_cursor(), 1);
+		assert_eq!(rotated.get_cursor(), 0);
+		assert!(buffer.counts_eq(&rotated));
+		assert_ne!(buffer, rotated);
+
+		rotated.add_failure();
+		assert!(!buffer.counts_eq(&rotated));
+		assert!(!buffer.counts_eq(&RingBuffer::new(4)));
+	}
+
+	#[test]
+	fn merge_test() {
+		let mut buffer = RingBuffer::new(3);
+		buffer.add_failure();
+		buffer.advance(1);
+		buffer.add_successes(2);
+		buffer.add_timeout();
+
+		let mut other = RingBuffer::new(3);
+		other.rotate_to(2);
+		other.add_successes(3);
+		other.advance(1);
+		other.add_failures(4);
+
+		assert!(buffer.merge(&other));
+		assert_eq!(buffer.get_cursor(), 1);
+		assert_eq!(
+			buffer.get_node_info(0),
+			NodeInfo {
+				failure_count: 1,
+				success_count: 3,
+			}
+		);
+		assert_eq!(
+			buffer.get_node_info(1),
+			NodeInfo {
+				failure_count: 5,
+				success_count: 2,
+			}
+		);
+		assert_eq!(buffer.get_timeout_count(), 1);
+
+		let before = buffer.clone();
+		assert!(!buffer.merge(&RingBuffer::new(4)));
+		assert_eq!(buffer, before);
+	}
+
+	#[test]
+	fn average_events_per_span_test() {
+		let mut buffer = RingBuffer::new(5);
+		assert_eq!(buffer.average_events_per_span(), 0.0);
+
+		buffer.add_successes(4);
+		buffer.add_failures(2);
+		buffer.advance(2);
+		buffer.add_successes(3);
+		buffer.advance(1);
+		// the current node is skipped
+		buffer.add_failures(100);
+		assert_eq!(buffer.average_events_per_span(), 4.5);
+	}
+
+	#[test]
+	fn oldest_to_newest_test() {
+		let mut buffer = RingBuffer::new(3);
+		buffer.add_failure();
+		buffer.advance(1);
+		buffer.add_successes(2);
+		buffer.advance(1);
+		buffer.add_successes(3);
+		buffer.advance(1);
+		buffer.add_failures(4);
+
+		assert_eq!(buffer.get_cursor(), 0);
+		assert_eq!(
+			buffer.oldest_to_newest(),
+			vec![
+				NodeInfo {
+					failure_count: 0,
+					success_count: 2,
+				},
+				NodeInfo {
+					failure_count: 0,
+					success_count: 3,
+				},
+				NodeInfo {
+					failure_count: 4,
+					success_count: 0,
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn add_timeout_test() {
+		let mut buffer = RingBuffer::new(2);
+		buffer.add_timeout();
+		buffer.add_failure();
+		buffer.advance(1);
+		buffer.add_timeout();
+		assert_eq!(buffer.get_timeout_count(), 2);
+		assert_eq!(buffer.nodes[0].timeout_count(), 1);
+		assert_eq!(buffer.nodes[0].failure_count(), 2);
+		assert_eq!(buffer.get_error_rate(1), 100.0);
+
+		buffer.advance(1);
+		assert_eq!(buffer.get_timeout_count(), 1);
+	}
+
+	#[test]
+	fn has_no_events_test() {
+		let mut buffer = RingBuffer::new(3);
+		assert!(buffer.has_no_events());
+		buffer.add_failure();
+		assert!(!buffer.has_no_events());
+		buffer.advance(3);
+		assert!(buffer.has_no_events());
+	}
+
+	#[test]
+	fn render_table_test() {
+		let mut buffer = RingBuffer::new(3);
+		buffer.add_successes(3);
+		buffer.add_failure();
+		buffer.advance(1);
+		buffer.add_successes(120);
+		assert_eq!(
+			buffer.render_table(),
+			"  index  success  failure    rate\n      0        3        1   25.00%\n*     1      120        0    0.00%\n      2        0        0    0.00%\n"
+		);
+	}
+
+	#[test]
+	fn node_info_test() {
+		let info = NodeInfo {
+			failure_count: 1,
+			success_count: 2,
+		};
+		assert_eq!(info.total(), 3);
+		assert_eq!(info.error_rate(), 33.33);
+		assert_eq!(
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+			.error_rate(),
+			0.0
+		);
+	}
+
 	#[test]
 	fn test_node_default() {
 		let node = Node::default();
@@ -167,6 +931,20 @@ mod test {
 		RingBuffer::new(0);
 	}
 
+	#[test]
+	fn clone_test() {
+		let mut buffer = RingBuffer::new(3);
+		buffer.add_success();
+		let mut cloned = buffer.clone();
+		assert_eq!(cloned, buffer);
+
+		cloned.add_failure();
+		cloned.advance(1);
+		assert_eq!(buffer.get_cursor(), 0);
+		assert_eq!(buffer.get_node_info(0).failure_count, 0);
+		assert_ne!(cloned, buffer);
+	}
+
 	#[test]
 	fn get_size_test() {
 		assert_eq!(RingBuffer::new(1).get_size(), 1);
@@ -179,6 +957,43 @@ mod test {
 		// TODO
 	}
 
+	#[test]
+	fn peek_next_cursor_test() {
+		let mut buffer = RingBuffer::new(3);
+		assert_eq!(buffer.peek_next_cursor(), 1);
+		buffer.advance(1);
+		assert_eq!(buffer.peek_next_cursor(), 2);
+		buffer.advance(1);
+		assert_eq!(buffer.get_cursor(), 2);
+		assert_eq!(buffer.peek_next_cursor(), 0); // wraps at the last index
+
+		assert_eq!(RingBuffer::new(1).peek_next_cursor(), 0);
+	}
+
+	#[test]
+	fn rotate_to_test() {
+		let mut buffer = RingBuffer::new(5);
+		buffer.add_failures(2);
+		buffer.advance(1);
+		buffer.add_successes(3);
+
+		buffer.rotate_to(3);
+		assert_eq!(buffer.get_cursor(), 3);
+		assert_eq!(buffer.get_node_info(0).failure_count, 2);
+		assert_eq!(buffer.get_node_info(1).success_count, 3);
+
+		buffer.rotate_to(1);
+		assert_eq!(buffer.get_node_info(1).success_count, 3);
+		buffer.add_success();
+		assert_eq!(buffer.get_node_info(1).success_count, 4);
+	}
+
+	#[test]
+	#[should_panic]
+	fn rotate_to_invalid_test() {
+		RingBuffer::new(5).rotate_to(5);
+	}
+
 	#[test]
 	fn advance_test() {
 		let mut rb = RingBuffer {
@@ -208,6 +1023,127 @@ mod test {
 		assert_eq!(rb.nodes[3].success_count, 5);
 	}
 
+	fn filled_buffer(cursor: usize) -> RingBuffer {
+		RingBuffer {
+			cursor,
+			nodes: (1..=4)
+				.map(|i| Node {
+					failure_count: i,
+					success_count: i,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				})
+				.collect(),
+		}
+	}
+
+	fn failures(buffer: &RingBuffer) -> Vec<usize> {
+		(0..buffer.get_size()).map(|i| buffer.get_node_info(i).failure_count).collect()
+	}
+
+	#[test]
+	fn advance_matrix_test() {
+		// steps < size: only the landing node holding old data is reset
+		let mut rb = filled_buffer(1);
+		rb.advance(1);
+		assert_eq!(rb.get_cursor(), 2);
+		assert_eq!(failures(&rb), vec![1, 2, 0, 4]);
+
+		// steps < size across the end of the buffer
+		let mut rb = filled_buffer(2);
+		rb.advance(3);
+		assert_eq!(rb.get_cursor(), 1);
+		assert_eq!(failures(&rb), vec![0, 0, 3, 0]);
+
+		// steps == size: a full lap lands on the current node and clears all
+		let mut rb = filled_buffer(2);
+		rb.advance(4);
+		assert_eq!(rb.get_cursor(), 2);
+		assert_eq!(failures(&rb), vec![0, 0, 0, 0]);
+
+		// steps > size
+		let mut rb = filled_buffer(2);
+		rb.advance(5);
+		assert_eq!(rb.get_cursor(), 3);
+		assert_eq!(failures(&rb), vec![0, 0, 0, 0]);
+
+		// steps == 0 only resets the current node
+		let mut rb = filled_buffer(2);
+		rb.advance(0);
+		assert_eq!(rb.get_cursor(), 2);
+		assert_eq!(failures(&rb), vec![1, 2, 0, 4]);
+	}
+
+	#[test]
+	fn advance_result_test() {
+		let mut rb = filled_buffer(1);
+		assert_eq!(
+			rb.advance(1),
+			AdvanceResult {
+				new_cursor: 2,
+				spans_moved: 1,
+				wrapped: false,
+				cleared_all: false,
+			}
+		);
+		assert_eq!(
+			rb.advance(2),
+			AdvanceResult {
+				new_cursor: 0,
+				spans_moved: 2,
+				wrapped: true,
+				cleared_all: false,
+			}
+		);
+		assert_eq!(
+			rb.advance(4),
+			AdvanceResult {
+				new_cursor: 0,
+				spans_moved: 4,
+				wrapped: true,
+				cleared_all: true,
+			}
+		);
+		assert_eq!(
+			rb.advance(7),
+			AdvanceResult {
+				new_cursor: 3,
+				spans_moved: 7,
+				wrapped: true,
+				cleared_all: true,
+			}
+		);
+	}
+
+	#[test]
+	fn advance_overflow_test() {
+		let mut rb = RingBuffer {
+			cursor: 3,
+			nodes: vec![
+				Node {
+					failure_count: 5,
+					success_count: 5,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				};
+				4
+			],
+		};
+
+		rb.advance(usize::MAX);
+		// usize::MAX % 4 == 3
+		assert_eq!(rb.get_cursor(), 2);
+		for i in 0..4 {
+			assert_eq!(
+				rb.get_node_info(i),
+				NodeInfo {
+					failure_count: 0,
+					success_count: 0,
+				}
+			);
+		}
+	}
+
 	#[test]
 	fn add_failure_success_test() {
 		let mut buffer = RingBuffer::new(1);
@@ -228,6 +1164,28 @@ mod test {
 		assert_eq!(buffer.get_node_info(buffer.cursor).success_count, 2);
 	}
 
+	#[test]
+	fn add_failures_successes_test() {
+		let mut buffer = RingBuffer::new(2);
+		buffer.add_successes(1000);
+		buffer.add_failures(3);
+		buffer.add_failure();
+		assert_eq!(
+			buffer.get_node_info(0),
+			NodeInfo {
+				failure_count: 4,
+				success_count: 1000,
+			}
+		);
+		assert_eq!(
+			buffer.get_node_info(1),
+			NodeInfo {
+				failure_count: 0,
+				success_count: 0,
+			}
+		);
+	}
+
 	#[test]
 	fn next_add_failure_success_test() {
 		let mut buffer = RingBuffer::new(5);
@@ -304,14 +1262,20 @@ mod test {
 				Node {
 					failure_count: 42,
 					success_count: 666,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 0,
 					success_count: 42,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 256,
 					success_count: 0,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 			],
 		};
@@ -348,14 +1312,20 @@ mod test {
 				Node {
 					failure_count: 42,
 					success_count: 666,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 0,
 					success_count: 42,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 256,
 					success_count: 0,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 			],
 		};
@@ -371,14 +1341,19 @@ mod test {
 				Node {
 					failure_count: 50,
 					success_count: 50,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 0,
 					success_count: 0,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 			],
 		};
 		assert_eq!(buffer.get_error_rate(10), 0.0); // cursor on first node
+		assert_eq!(buffer.get_error_rate_including_current(10), 50.0);
 
 		let buffer = RingBuffer {
 			cursor: 1,
@@ -386,10 +1361,14 @@ mod test {
 				Node {
 					failure_count: 50,
 					success_count: 50,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 0,
 					success_count: 0,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 			],
 		};
@@ -401,14 +1380,20 @@ mod test {
 				Node {
 					failure_count: 0,
 					success_count: 0,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 50,
 					success_count: 50,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 10,
 					success_count: 90,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 			],
 		};
@@ -420,17 +1405,179 @@ mod test {
 				Node {
 					failure_count: 0,
 					success_count: 0,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 5,
 					success_count: 5,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 				Node {
 					failure_count: 1,
 					success_count: 9,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
 				},
 			],
 		};
 		assert_eq!(buffer.get_error_rate(100), 0.0); // 6 of 20 = 30% but less than min_eval_size
 	}
+
+	#[test]
+	fn error_rate_parts_test() {
+		let buffer = RingBuffer {
+			cursor: 0,
+			nodes: vec![
+				Node {
+					failure_count: 99,
+					success_count: 99,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				},
+				Node {
+					failure_count: 5,
+					success_count: 5,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				},
+				Node {
+					failure_count: 1,
+					success_count: 9,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				},
+			],
+		};
+		assert_eq!(buffer.error_rate_parts(0), Some((6, 20))); // cursor node is skipped
+		assert_eq!(buffer.error_rate_parts(20), Some((6, 20)));
+		assert_eq!(buffer.error_rate_parts(21), None); // less than min_eval_size
+		assert_eq!(RingBuffer::new(3).error_rate_parts(0), None);
+	}
+
+	#[test]
+	fn worst_node_test() {
+		let buffer = RingBuffer {
+			cursor: 0,
+			nodes: vec![
+				Node {
+					failure_count: 100,
+					success_count: 0,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				},
+				Node {
+					failure_count: 1,
+					success_count: 9,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				},
+				Node {
+					failure_count: 0,
+					success_count: 0,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				},
+				Node {
+					failure_count: 8,
+					success_count: 2,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				},
+				Node {
+					failure_count: 1,
+					success_count: 1,
+					timeout_count: 0,
+					latency_buckets: [0; 4],
+				},
+			],
+		};
+		assert_eq!(buffer.worst_node(0), Some((3, 80.0))); // cursor node is skipped
+		assert_eq!(buffer.worst_node(5), Some((3, 80.0)));
+		assert_eq!(buffer.worst_node(11), None);
+		assert_eq!(RingBuffer::new(3).worst_node(0), None);
+	}
+
+	#[test]
+	fn resize_test() {
+		let nodes = vec![
+			Node {
+				failure_count: 1,
+				success_count: 0,
+				timeout_count: 0,
+				latency_buckets: [0; 4],
+			},
+			Node {
+				failure_count: 2,
+				success_count: 0,
+				timeout_count: 0,
+				latency_buckets: [0; 4],
+			},
+			Node {
+				failure_count: 3,
+				success_count: 0,
+				timeout_count: 0,
+				latency_buckets: [0; 4],
+			},
+			Node {
+				failure_count: 4,
+				success_count: 0,
+				timeout_count: 0,
+				latency_buckets: [0; 4],
+			},
+		];
+
+		let mut buffer = RingBuffer {
+			cursor: 1,
+			nodes: nodes.clone(),
+		};
+		buffer.resize(6);
+		assert_eq!(buffer.get_size(), 6);
+		assert_eq!(buffer.get_cursor(), 3);
+		let failures: Vec<usize> = (0..6).map(|i| buffer.get_node_info(i).failure_count).collect();
+		assert_eq!(failures, vec![3, 4, 1, 2, 0, 0]);
+
+		let mut buffer = RingBuffer {
+			cursor: 1,
+			nodes: nodes.clone(),
+		};
+		buffer.resize(2);
+		assert_eq!(buffer.get_size(), 2);
+		assert_eq!(buffer.get_cursor(), 1);
+		let failures: Vec<usize> = (0..2).map(|i| buffer.get_node_info(i).failure_count).collect();
+		assert_eq!(failures, vec![1, 2]);
+
+		let mut buffer = RingBuffer { cursor: 1, nodes };
+		buffer.resize(4);
+		assert_eq!(buffer.get_cursor(), 3);
+		let failures: Vec<usize> = (0..4).map(|i| buffer.get_node_info(i).failure_count).collect();
+		assert_eq!(failures, vec![3, 4, 1, 2]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn resize_invalid_test() {
+		RingBuffer::new(3).resize(0);
+	}
+
+	#[test]
+	fn fold_nodes_test() {
+		let mut buffer = RingBuffer::new(4);
+		buffer.add_failures(3);
+		buffer.advance(2);
+		buffer.add_failures(4);
+		buffer.add_successes(10);
+
+		assert_eq!(buffer.fold_nodes(0, |total, _, info| total + info.failure_count), 7);
+		assert_eq!(
+			buffer.fold_nodes(Vec::new(), |mut indexes, i, info| {
+				if info.success_count > 0 {
+					indexes.push(i);
+				}
+				indexes
+			}),
+			vec![2]
+		);
+	}
 }
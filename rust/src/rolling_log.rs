@@ -0,0 +1,123 @@
+//! A rolling window over the last `capacity` individual events
+use std::collections::VecDeque;
+
+use crate::{ring_buffer::percentage, window::Window};
+
+/// Stores the outcome of each event, `true` being a success, and evicts the
+/// oldest event once `capacity` is reached
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollingLog {
+	capacity: usize,
+	events: VecDeque<bool>,
+}
+
+impl RollingLog {
+	/// Create a new rolling log holding at most `capacity` events
+	pub fn new(capacity: usize) -> Self {
+		if capacity == 0 {
+			panic!("You must at least have a capacity of one event in your rolling log");
+		}
+
+		Self {
+			capacity,
+			events: VecDeque::with_capacity(capacity),
+		}
+	}
+
+	/// Returns the maximum number of events we keep
+	pub fn get_capacity(&self) -> usize {
+		self.capacity
+	}
+
+	/// Returns the number of events currently stored
+	pub fn len(&self) -> usize {
+		self.events.len()
+	}
+
+	/// Returns true if no events have been recorded yet
+	pub fn is_empty(&self) -> bool {
+		self.events.is_empty()
+	}
+
+	fn push(&mut self, success: bool) {
+		if self.events.len() >= self.capacity {
+			self.events.pop_front();
+		}
+		self.events.push_back(success);
+	}
+}
+
+impl Window for RollingLog {
+	fn add_success(&mut self) {
+		self.push(true);
+	}
+
+	fn add_failure(&mut self) {
+		self.push(false);
+	}
+
+	fn evaluated_events(&self) -> usize {
+		self.events.len()
+	}
+
+	fn get_error_rate(&self, min_eval_size: usize) -> f32 {
+		let total = self.events.len();
+		let failures = self.events.iter().filter(|success| !**success).count();
+
+		if total < min_eval_size || total == 0 {
+			0.0
+		} else {
+			percentage(failures, total)
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn new_test() {
+		assert_eq!(RollingLog::new(1).get_capacity(), 1);
+		assert_eq!(RollingLog::new(50).get_capacity(), 50);
+		assert!(RollingLog::new(50).is_empty());
+	}
+
+	#[test]
+	#[should_panic]
+	fn new_invalid_test() {
+		RollingLog::new(0);
+	}
+
+	#[test]
+	fn evicts_oldest_test() {
+		let mut log = RollingLog::new(4);
+		log.add_failure();
+		log.add_failure();
+		log.add_success();
+		log.add_success();
+		assert_eq!(log.len(), 4);
+		assert_eq!(log.get_error_rate(4), 50.0);
+
+		// the two failures are the oldest events and get evicted first
+		log.add_success();
+		assert_eq!(log.len(), 4);
+		assert_eq!(log.get_error_rate(4), 25.0);
+		log.add_success();
+		assert_eq!(log.len(), 4);
+		assert_eq!(log.get_error_rate(4), 0.0);
+		log.add_failure();
+		assert_eq!(log.get_error_rate(4), 25.0);
+	}
+
+	#[test]
+	fn get_error_rate_test() {
+		let mut log = RollingLog::new(10);
+		assert_eq!(log.get_error_rate(0), 0.0);
+		log.add_failure();
+		log.add_success();
+		log.add_success();
+		assert_eq!(log.get_error_rate(3), 33.33);
+		assert_eq!(log.get_error_rate(4), 0.0); // less than min_eval_size
+	}
+}
@@ -1,12 +1,19 @@
 use std::{
 	io::{self, Read},
 	process::Command,
-	sync::mpsc,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc, Arc,
+	},
 	thread,
 	time::{Duration, Instant},
 };
 
-use crate::circuit_breaker::{CircuitBreaker, State};
+use crate::{
+	circuit_breaker::{CircuitBreaker, State},
+	cli_helpers::run_command,
+	window::WindowMode,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum MiddleBuffer {
@@ -20,47 +27,19 @@ pub struct Visualizer<'a> {
 	top: Vec<usize>,
 	middle: Option<Vec<MiddleBuffer>>,
 	bottom: Option<Vec<usize>>,
+	watch: Option<String>,
+	minimal: bool,
 }
 
 impl<'a> Visualizer<'a> {
 	pub fn new(cb: &'a mut CircuitBreaker) -> Self {
-		match cb.get_buffer().get_size() {
-			1 => Self {
-				cb,
-				top: vec![0],
-				middle: None,
-				bottom: None,
-			},
-			2 => Self {
-				cb,
-				top: vec![0, 1],
-				middle: None,
-				bottom: None,
-			},
-			3 => Self {
-				cb,
-				top: vec![0, 1, 2],
-				middle: None,
-				bottom: None,
-			},
-			4 => Self {
-				cb,
-				top: vec![0, 1, 2],
-				middle: None,
-				bottom: Some(vec![3]),
-			},
-			5 => Self {
-				cb,
-				top: vec![0, 1, 2],
-				middle: None,
-				bottom: Some(vec![4, 3]),
-			},
-			6 => Self {
-				cb,
-				top: vec![0, 1, 2],
-				middle: None,
-				bottom: Some(vec![5, 4, 3]),
-			},
+		let (top, middle, bottom) = match cb.get_buffer().get_size() {
+			1 => (vec![0], None, None),
+			2 => (vec![0, 1], None, None),
+			3 => (vec![0, 1, 2], None, None),
+			4 => (vec![0, 1, 2], None, Some(vec![3])),
+			5 => (vec![0, 1, 2], None, Some(vec![4, 3])),
+			6 => (vec![0, 1, 2], None, Some(vec![5, 4, 3])),
 			length => {
 				// safe because we are in a match with length > 6
 				#[allow(clippy::arithmetic_side_effects)]
@@ -98,34 +77,57 @@ impl<'a> Visualizer<'a> {
 					}
 				}
 
-				Self {
-					cb,
-					top: vec![0, 1, 2],
-					middle: Some(middle_buffers),
-					bottom: Some(bottom),
-				}
+				(vec![0, 1, 2], Some(middle_buffers), Some(bottom))
 			},
+		};
+
+		Self {
+			cb,
+			top,
+			middle,
+			bottom,
+			watch: None,
+			minimal: false,
 		}
 	}
 
-	fn render_buffer_box_top(&mut self, index: usize) -> String {
-		let is_active = if self.cb.get_state() == State::Closed {
-			self.cb.get_buffer().get_cursor() == index
+	/// Run `command` every second and record its exit code
+	pub fn with_watch(mut self, command: Option<String>) -> Self {
+		self.watch = command;
+		self
+	}
+
+	/// Render the compact view with one line per span, e.g. for narrow
+	/// terminals or tmux splits
+	pub fn with_minimal(mut self, minimal: bool) -> Self {
+		self.minimal = minimal;
+		self
+	}
+
+	fn is_active(&mut self, index: usize) -> bool {
+		self.cb.get_state() == State::Closed && self.cb.get_buffer().get_cursor() == index
+	}
+
+	fn is_next(&mut self, index: usize) -> bool {
+		if self.cb.get_state() == State::Closed {
+			let buffer = self.cb.get_buffer();
+			buffer.get_cursor() != index && buffer.peek_next_cursor() == index
 		} else {
 			false
-		};
-		match is_active {
-			true => String::from("┏━━━━━━━━━━━━━━━━━┓"),
-			false => String::from("┌─────────────────┐"),
+		}
+	}
+
+	fn render_buffer_box_top(&mut self, index: usize) -> String {
+		let is_active = self.is_active(index);
+		match (is_active, self.is_next(index)) {
+			(true, _) => String::from("┏━━━━━━━━━━━━━━━━━┓"),
+			(false, true) => String::from("┌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌┐"),
+			(false, false) => String::from("┌─────────────────┐"),
 		}
 	}
 
 	fn render_buffer_box_middle(&mut self, index: usize) -> String {
-		let is_active = if self.cb.get_state() == State::Closed {
-			self.cb.get_buffer().get_cursor() == index
-		} else {
-			false
-		};
+		let is_active = self.is_active(index);
 		let infos = self.cb.get_buffer().get_node_info(index);
 		match is_active {
 			true => format!(
@@ -140,14 +142,11 @@ impl<'a> Visualizer<'a> {
 	}
 
 	fn render_buffer_box_bottom(&mut self, index: usize) -> String {
-		let is_active = if self.cb.get_state() == State::Closed {
-			self.cb.get_buffer().get_cursor() == index
-		} else {
-			false
-		};
-		match is_active {
-			true => String::from("┗━━━━━━━━━━━━━━━━━┛"),
-			false => String::from("└─────────────────┘"),
+		let is_active = self.is_active(index);
+		match (is_active, self.is_next(index)) {
+			(true, _) => String::from("┗━━━━━━━━━━━━━━━━━┛"),
+			(false, true) => String::from("└╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌┘"),
+			(false, false) => String::from("└─────────────────┘"),
 		}
 	}
 
@@ -184,19 +183,20 @@ impl<'a> Visualizer<'a> {
 		output.push_str("\n                              │");
 		output.push_str("\n                              ▼\x1b[0m");
 		output.push_str(&format!("\n                         Status: {state}"));
-		output.push_str(&format!("\n                     Error Rate: {:0<6?}%\n", self.cb.get_error_rate()));
+		output.push_str(&format!("\n                     Error Rate: {:<7}\n", self.cb.error_rate_display()));
 		match state {
-			State::Closed => {
-				let buffer_span_duration = self.cb.get_settings().buffer_span_duration;
-				let timer = self
-					.cb
-					.get_settings()
-					.buffer_span_duration
-					.saturating_sub(self.cb.get_elapsed_time(buffer_span_duration, Instant::now()));
-				output.push_str(&format!("                    Next Buffer: {}s   \n", timer.as_secs()));
+			State::Closed => match self.cb.get_settings().window_mode {
+				WindowMode::SpanEvents(span_events) => {
+					let events = span_events.max(1).saturating_sub(self.cb.get_span_event_count());
+					output.push_str(&format!("                    Next Buffer: {events} events   \n"));
+				},
+				_ => {
+					let timer = self.cb.time_budget_remaining(Instant::now());
+					output.push_str(&format!("                    Next Buffer: {}s   \n", timer.as_secs()));
+				},
 			},
 			State::Open(duration) => {
-				let timer = self.cb.get_settings().retry_timeout.saturating_sub(duration.elapsed());
+				let timer = self.cb.effective_retry_timeout().saturating_sub(duration.elapsed());
 				output.push_str(&format!("                          Retry: {}s   \n", timer.as_secs()));
 			},
 			State::HalfOpen => {
@@ -351,6 +351,43 @@ impl<'a> Visualizer<'a> {
 		output
 	}
 
+	/// Render a compact view of the circuit breaker with a single line per span
+	/// which fits into 40 columns
+	pub fn render_minimal<T, E>(&mut self, input: Option<Result<T, E>>) -> String {
+		let request = match input {
+			None => "",
+			Some(Ok(_)) => "  \x1b[32mSuccess\x1b[0m",
+			Some(Err(_)) => "  \x1b[31mFailure\x1b[0m",
+		};
+		let state = self.cb.get_state();
+		let mut output = format!(
+			"\nStatus: {}{:<11}\x1b[0m{request}\nError Rate: {}\n\n",
+			state.ansi_color(),
+			format!(" {} ", state.label()),
+			self.cb.error_rate_display()
+		);
+
+		for index in 0..self.cb.get_buffer().get_size() {
+			let marker = if self.is_active(index) { '▶' } else { ' ' };
+			let infos = self.cb.get_buffer().get_node_info(index);
+			output.push_str(&format!(
+				"{marker} B{index:<2} \x1b[42m {:0>3} \x1b[0m \x1b[41m {:0>3} \x1b[0m\n",
+				infos.success_count, infos.failure_count
+			));
+		}
+
+		output.push_str("\n[s]=Success [f]=Failure [q]=Quit\n");
+		output
+	}
+
+	fn render_view<T, E>(&mut self, input: Option<Result<T, E>>) -> String {
+		if self.minimal {
+			self.render_minimal(input)
+		} else {
+			self.render(input)
+		}
+	}
+
 	pub fn start(&mut self, periodically: bool) -> io::Result<()> {
 		#[cfg(target_os = "windows")]
 		compile_error!(
@@ -359,23 +396,26 @@ impl<'a> Visualizer<'a> {
 
 		let _raw = RawMode::enter()?;
 
-		// A thread just for stdin
+		// A thread just for stdin which stops once we signal the shutdown
 		let (sender, receiver) = mpsc::channel::<u8>();
-		{
-			let stdin = io::stdin();
-			thread::spawn(move || {
-				let mut lock = stdin.lock();
-				let mut buffer = [0u8; 1];
-				while lock.read_exact(&mut buffer).is_ok() {
-					if sender.send(buffer[0]).is_err() {
-						break;
-					}
-				}
-			});
-		}
+		let shutdown = Shutdown::default();
+		let reader = {
+			let shutdown = shutdown.clone();
+			thread::spawn(move || forward_input(io::stdin().lock(), &sender, &shutdown))
+		};
+
+		// The watched command runs on its own thread so a slow command doesn't
+		// block the input. The thread isn't joined as the command may hang, it
+		// stops once we signal the shutdown or drop the receiver.
+		let watch_results = self.watch.clone().map(|command| {
+			let (sender, receiver) = mpsc::channel();
+			let shutdown = shutdown.clone();
+			thread::spawn(move || watch_command(&command, &sender, &shutdown));
+			receiver
+		});
 
 		let mut last_tick = Instant::now();
-		let render = self.render::<(), &str>(None);
+		let render = self.render_view::<(), &str>(None);
 		let lines = render.bytes().filter(|&b| b == b'\n').count();
 		let reset_pos = format!("\x1b[{lines}F");
 		print!("{render}");
@@ -389,19 +429,19 @@ impl<'a> Visualizer<'a> {
 					},
 					's' => {
 						self.record::<(), &str>(Ok(()));
-						print!("{reset_pos}{}", self.render::<(), &str>(Some(Ok(()))));
+						print!("{reset_pos}{}", self.render_view::<(), &str>(Some(Ok(()))));
 						last_tick = Instant::now();
 					},
 					'f' => {
 						self.record::<(), &str>(Err(""));
-						print!("{reset_pos}{}", self.render::<(), &str>(Some(Err(""))));
+						print!("{reset_pos}{}", self.render_view::<(), &str>(Some(Err(""))));
 						last_tick = Instant::now();
 					},
 					'x' => {
 						// Debug output and quit
 						println!(
-							"\n ╔╦╗ ╔═╗ ╔╗  ╦ ╦ ╔═╗\n  ║║ ║╣  ╠╩╗ ║ ║ ║ ╦\n ═╩╝ ╚═╝ ╚═╝ ╚═╝ ╚═╝\n\n{:#?}",
-							self.cb.get_buffer()
+							"\n ╔╦╗ ╔═╗ ╔╗  ╦ ╦ ╔═╗\n  ║║ ║╣  ╠╩╗ ║ ║ ║ ╦\n ═╩╝ ╚═╝ ╚═╝ ╚═╝ ╚═╝\n\n{}",
+							self.cb.get_buffer().render_table()
 						);
 						break;
 					},
@@ -409,21 +449,74 @@ impl<'a> Visualizer<'a> {
 				}
 			}
 
-			if periodically && last_tick.elapsed() >= Duration::from_secs(1) {
-				print!("{reset_pos}{}", self.render::<(), &str>(None));
+			if let Some(result) = watch_results.as_ref().and_then(|results| results.try_recv().ok()) {
+				self.record(result.clone());
+				print!("{reset_pos}{}", self.render_view(Some(result)));
+				last_tick = Instant::now();
+			} else if periodically && last_tick.elapsed() >= Duration::from_secs(1) {
+				print!("{reset_pos}{}", self.render_view::<(), &str>(None));
 				last_tick = Instant::now();
 			}
 		}
 
+		// join the reader before `_raw` restores the terminal
+		shutdown.signal();
+		let _ = reader.join();
+
 		Ok(())
 	}
 }
 
+/// A flag shared with the stdin thread to tell it to stop reading
+#[derive(Debug, Clone, Default)]
+struct Shutdown(Arc<AtomicBool>);
+
+impl Shutdown {
+	fn signal(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	fn is_signaled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// Forward each byte of `input` to `sender` until we are signaled to shut down.
+/// In raw mode reads time out so the flag is checked even without input.
+fn forward_input(mut input: impl Read, sender: &mpsc::Sender<u8>, shutdown: &Shutdown) {
+	let mut buffer = [0u8; 1];
+	while !shutdown.is_signaled() {
+		match input.read(&mut buffer) {
+			Ok(0) => thread::sleep(Duration::from_millis(10)),
+			Ok(_) => {
+				if sender.send(buffer[0]).is_err() {
+					break;
+				}
+			},
+			Err(_) => break,
+		}
+	}
+}
+
+/// Run `command` every second and send its result to `sender` until we are
+/// signaled to shut down
+fn watch_command(command: &str, sender: &mpsc::Sender<Result<(), String>>, shutdown: &Shutdown) {
+	while !shutdown.is_signaled() {
+		let started = Instant::now();
+		if sender.send(run_command(command)).is_err() {
+			break;
+		}
+		thread::sleep(Duration::from_secs(1).saturating_sub(started.elapsed()));
+	}
+}
+
 struct RawMode;
 
 impl RawMode {
 	fn enter() -> io::Result<Self> {
-		Command::new("stty").arg("-icanon").arg("-echo").spawn()?.wait()?;
+		// `min 0 time 1` lets reads time out after 100ms so the stdin thread can
+		// notice a shutdown
+		Command::new("stty").args(["-icanon", "-echo", "min", "0", "time", "1"]).spawn()?.wait()?;
 		Ok(RawMode)
 	}
 }
@@ -439,6 +532,39 @@ mod test {
 	use super::*;
 	use crate::circuit_breaker::{CircuitBreaker, Settings};
 
+	#[test]
+	fn forward_input_shutdown_test() {
+		let (sender, receiver) = mpsc::channel::<u8>();
+		let shutdown = Shutdown::default();
+		let reader = {
+			let shutdown = shutdown.clone();
+			thread::spawn(move || forward_input(io::Cursor::new(b"sf".to_vec()), &sender, &shutdown))
+		};
+
+		assert_eq!(receiver.recv_timeout(Duration::from_secs(1)), Ok(b's'));
+		assert_eq!(receiver.recv_timeout(Duration::from_secs(1)), Ok(b'f'));
+		assert!(!shutdown.is_signaled());
+
+		// the reader keeps polling without input until signaled
+		shutdown.signal();
+		assert!(reader.join().is_ok());
+		assert!(receiver.try_recv().is_err());
+	}
+
+	#[test]
+	fn watch_command_test() {
+		let (sender, receiver) = mpsc::channel();
+		let shutdown = Shutdown::default();
+		let watcher = {
+			let shutdown = shutdown.clone();
+			thread::spawn(move || watch_command("exit 3", &sender, &shutdown))
+		};
+
+		assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok_and(|result| result.is_err()));
+		shutdown.signal();
+		assert!(watcher.join().is_ok());
+	}
+
 	#[test]
 	fn render_buffer_box_test() {
 		let mut cb = CircuitBreaker::new(Settings { ..Settings::default() });
@@ -447,9 +573,13 @@ mod test {
 		assert_eq!(vis.render_buffer_box_middle(0), String::from("┃ B0  \x1b[42m 000 \x1b[0m \x1b[41m 000 \x1b[0m ┃"));
 		assert_eq!(vis.render_buffer_box_bottom(0), String::from("┗━━━━━━━━━━━━━━━━━┛"));
 
-		assert_eq!(vis.render_buffer_box_top(1), String::from("┌─────────────────┐"));
+		assert_eq!(vis.render_buffer_box_top(1), String::from("┌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌┐"));
 		assert_eq!(vis.render_buffer_box_middle(1), String::from("│ B1  \x1b[42m 000 \x1b[0m \x1b[41m 000 \x1b[0m │"));
-		assert_eq!(vis.render_buffer_box_bottom(1), String::from("└─────────────────┘"));
+		assert_eq!(vis.render_buffer_box_bottom(1), String::from("└╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌╌┘"));
+
+		assert_eq!(vis.render_buffer_box_top(2), String::from("┌─────────────────┐"));
+		assert_eq!(vis.render_buffer_box_middle(2), String::from("│ B2  \x1b[42m 000 \x1b[0m \x1b[41m 000 \x1b[0m │"));
+		assert_eq!(vis.render_buffer_box_bottom(2), String::from("└─────────────────┘"));
 
 		vis.record::<(), &str>(Err(""));
 		vis.record::<(), ()>(Ok(()));
@@ -462,6 +592,67 @@ mod test {
 		assert_eq!(vis.render_buffer_box_bottom(0), String::from("┗━━━━━━━━━━━━━━━━━┛"));
 	}
 
+	#[test]
+	fn render_minimal_test() {
+		let mut cb = CircuitBreaker::new(Settings {
+			buffer_size: 1,
+			..Settings::default()
+		});
+		let mut vis = Visualizer::new(&mut cb).with_minimal(true);
+		vis.record::<(), ()>(Ok(()));
+		assert_eq!(
+			vis.render_view::<(), ()>(None),
+			"\nStatus:  Closed    \x1b[0m\nError Rate: 0.00%\n\n\
+			▶ B0  \x1b[42m 001 \x1b[0m \x1b[41m 000 \x1b[0m\n\
+			\n[s]=Success [f]=Failure [q]=Quit\n"
+		);
+
+		let mut cb = CircuitBreaker::new(Settings {
+			buffer_size: 3,
+			..Settings::default()
+		});
+		let mut vis = Visualizer::new(&mut cb);
+		vis.record::<(), &str>(Err(""));
+		assert_eq!(
+			vis.render_minimal::<(), &str>(Some(Err(""))),
+			"\nStatus:  Closed    \x1b[0m  \x1b[31mFailure\x1b[0m\nError Rate: 0.00%\n\n\
+			▶ B0  \x1b[42m 000 \x1b[0m \x1b[41m 001 \x1b[0m\n  \
+			B1  \x1b[42m 000 \x1b[0m \x1b[41m 000 \x1b[0m\n  \
+			B2  \x1b[42m 000 \x1b[0m \x1b[41m 000 \x1b[0m\n\
+			\n[s]=Success [f]=Failure [q]=Quit\n"
+		);
+
+		let mut cb = CircuitBreaker::new(Settings {
+			buffer_size: 12,
+			..Settings::default()
+		});
+		let mut vis = Visualizer::new(&mut cb);
+		let output = vis.render_minimal::<(), ()>(None);
+		assert_eq!(output.lines().count(), 18);
+		assert!(output.contains("  B11 "));
+		// the colored cells don't take up columns
+		assert!(output.lines().all(|line| line
+			.replace("\x1b[0m", "")
+			.replace("\x1b[42m", "")
+			.replace("\x1b[41m", "")
+			.chars()
+			.count()
+			<= 40));
+	}
+
+	#[test]
+	fn new_minimal_test() {
+		// every layout starts with the full view
+		for buffer_size in 1..=8 {
+			let mut cb = CircuitBreaker::new(Settings {
+				buffer_size,
+				..Settings::default()
+			});
+			assert!(!Visualizer::new(&mut cb).minimal);
+			assert!(Visualizer::new(&mut cb).with_minimal(true).minimal);
+		}
+	}
+
 	#[test]
 	#[should_panic]
 	fn new_invalid_test() {
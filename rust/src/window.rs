@@ -0,0 +1,41 @@
+//! The storage models a [CircuitBreaker](crate::CircuitBreaker) can evaluate
+//! its error rate over
+
+/// Which storage model the [CircuitBreaker](crate::CircuitBreaker) records into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowMode {
+	/// Record into the time-based [RingBuffer](crate::RingBuffer) spans
+	Spans,
+	/// Record into the [RingBuffer](crate::RingBuffer) spans but advance to the
+	/// next span after every N events instead of after
+	/// `Settings.buffer_span_duration`
+	SpanEvents(usize),
+	/// Record each outcome into a [RollingLog](crate::RollingLog) which drops the
+	/// oldest event once it holds `capacity` events
+	RollingLog(usize),
+}
+
+/// A window of recorded events we can calculate an error rate for
+pub trait Window {
+	/// Record a successful event
+	fn add_success(&mut self);
+	/// Record a failed event
+	fn add_failure(&mut self);
+	/// Record `count` successful events at once
+	fn add_successes(&mut self, count: usize) {
+		for _ in 0..count {
+			self.add_success();
+		}
+	}
+	/// Record `count` failed events at once
+	fn add_failures(&mut self, count: usize) {
+		for _ in 0..count {
+			self.add_failure();
+		}
+	}
+	/// Returns the number of events the error rate is calculated from
+	fn evaluated_events(&self) -> usize;
+	/// Returns the error rate as a percentage (0.0 to 100.0) or 0.0 if there are
+	/// less than `min_eval_size` events to evaluate
+	fn get_error_rate(&self, min_eval_size: usize) -> f32;
+}